@@ -0,0 +1,250 @@
+use std::path::PathBuf;
+
+use crate::{
+    config::cfg::{DownloadOptions, DownloadOrder, FilterPatterns},
+    download::{stray::CleanAction, voice::parse_voice_languages},
+};
+
+pub enum Command {
+    Download,
+    Resume(Option<PathBuf>),
+    Update(Option<PathBuf>),
+    Apply(Option<PathBuf>),
+    Rollback(Option<PathBuf>),
+    Clean(Option<PathBuf>),
+}
+
+pub struct Args {
+    pub command: Command,
+    pub fsync: bool,
+    pub backup: bool,
+    pub keep_backups: Option<usize>,
+    pub filters: FilterPatterns,
+    pub no_filters: bool,
+    pub dry_run: bool,
+    pub plan_file: Option<PathBuf>,
+    pub order: Option<DownloadOrder>,
+    pub clean_action: Option<CleanAction>,
+    pub yes: bool,
+    pub keep: Vec<String>,
+    pub link_roots: Vec<PathBuf>,
+    pub cache_dir: Option<PathBuf>,
+    pub cache_limit: Option<u64>,
+    pub targets: Vec<(String, PathBuf)>,
+    pub release: Option<String>,
+    pub catalogs: Vec<String>,
+}
+
+fn expand_path(path: &str) -> PathBuf {
+    PathBuf::from(shellexpand::tilde(path).into_owned())
+}
+
+fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        "T" | "TB" | "TIB" => 1 << 40,
+        _ => return Err(format!("Invalid size: {}", value)),
+    };
+
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(multiplier))
+        .ok_or_else(|| format!("Invalid size: {}", value))
+}
+
+impl Args {
+    pub fn apply_to(&self, options: &mut DownloadOptions) {
+        options.fsync = self.fsync;
+        options.backup = self.backup;
+        if let Some(keep) = self.keep_backups {
+            options.keep_backups = keep;
+        }
+        options.link_roots.extend(self.link_roots.iter().cloned());
+        if let Some(cache_dir) = &self.cache_dir {
+            options.cache_dir = Some(cache_dir.clone());
+        }
+        if let Some(limit) = self.cache_limit {
+            options.cache_limit = Some(limit);
+        }
+        if let Some(order) = self.order {
+            options.order = order;
+        }
+        options
+            .filters
+            .include
+            .extend(self.filters.include.iter().cloned());
+        options
+            .filters
+            .exclude
+            .extend(self.filters.exclude.iter().cloned());
+        if self.filters.voice_languages.is_some() {
+            options.filters.voice_languages = self.filters.voice_languages.clone();
+        }
+    }
+
+    pub fn parse() -> Result<Self, String> {
+        Self::parse_from(std::env::args().skip(1))
+    }
+
+    pub fn parse_from<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut args = args.into_iter();
+        let mut positional = Vec::new();
+        let mut fsync = false;
+        let mut backup = false;
+        let mut keep_backups = None;
+        let mut filters = FilterPatterns::default();
+        let mut no_filters = false;
+        let mut dry_run = false;
+        let mut plan_file = None;
+        let mut order = None;
+        let mut clean_action = None;
+        let mut yes = false;
+        let mut keep = Vec::new();
+        let mut link_roots = Vec::new();
+        let mut cache_dir = None;
+        let mut cache_limit = None;
+        let mut targets = Vec::new();
+        let mut release = None;
+        let mut catalogs = Vec::new();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--fsync" => fsync = true,
+                "--backup" => backup = true,
+                "--keep-backups" => {
+                    let value = args.next().ok_or("--keep-backups requires a number")?;
+                    // Keeping zero would prune the backup this very run relies on for rollback.
+                    keep_backups = match value.parse() {
+                        Ok(0) => return Err("--keep-backups must be at least 1".to_string()),
+                        Ok(keep) => Some(keep),
+                        Err(_) => return Err(format!("Invalid --keep-backups value: {}", value)),
+                    };
+                }
+                "--dry-run" => dry_run = true,
+                "--plan-file" => {
+                    let value = args.next().ok_or("--plan-file requires a path")?;
+                    plan_file = Some(expand_path(&value));
+                    dry_run = true;
+                }
+                "--order" => {
+                    let value = args.next().ok_or("--order requires a strategy")?;
+                    order = Some(value.parse()?);
+                }
+                "--delete" => clean_action = Some(CleanAction::Delete),
+                "--quarantine" => clean_action = Some(CleanAction::Quarantine),
+                "--yes" => yes = true,
+                "--keep" => keep.push(args.next().ok_or("--keep requires a pattern")?),
+                "--link-from" => {
+                    let value = args.next().ok_or("--link-from requires a folder")?;
+                    link_roots.push(expand_path(&value));
+                }
+                "--cache" => {
+                    let value = args.next().ok_or("--cache requires a folder")?;
+                    cache_dir = Some(expand_path(&value));
+                }
+                "--cache-limit" => {
+                    let value = args.next().ok_or("--cache-limit requires a size")?;
+                    cache_limit = Some(parse_size(&value)?);
+                }
+                "--catalog" => {
+                    catalogs.push(args.next().ok_or("--catalog requires a URL or path")?)
+                }
+                "--release" => {
+                    release = Some(args.next().ok_or("--release requires a name")?);
+                }
+                "--target" => {
+                    let value = args.next().ok_or("--target requires SPEC=FOLDER")?;
+                    let (spec, folder) = value.split_once('=').ok_or_else(|| {
+                        format!("Invalid --target {:?}, expected SPEC=FOLDER", value)
+                    })?;
+                    targets.push((spec.to_string(), expand_path(folder)));
+                }
+                "--include" => filters
+                    .include
+                    .push(args.next().ok_or("--include requires a pattern")?),
+                "--exclude" => filters
+                    .exclude
+                    .push(args.next().ok_or("--exclude requires a pattern")?),
+                "--no-filters" => no_filters = true,
+                "--voice" => {
+                    let value = args.next().ok_or("--voice requires a language list")?;
+                    filters.voice_languages = Some(parse_voice_languages(&value)?);
+                }
+                flag if flag.starts_with("--") => {
+                    return Err(format!("Unknown option: {}", flag));
+                }
+                _ => positional.push(arg),
+            }
+        }
+
+        let mut positional = positional.into_iter();
+        let command = match positional.next().as_deref() {
+            None => Command::Download,
+            Some("resume") => Command::Resume(positional.next().as_deref().map(expand_path)),
+            Some("update") => Command::Update(positional.next().as_deref().map(expand_path)),
+            Some("apply") => Command::Apply(positional.next().as_deref().map(expand_path)),
+            Some("rollback") => Command::Rollback(positional.next().as_deref().map(expand_path)),
+            Some("clean") => Command::Clean(positional.next().as_deref().map(expand_path)),
+            Some(other) => return Err(format!("Unknown command: {}", other)),
+        };
+
+        if let Some(extra) = positional.next() {
+            return Err(format!("Unexpected argument: {}", extra));
+        }
+        if !targets.is_empty() && !matches!(command, Command::Download) {
+            return Err("--target can only be used with a plain download".to_string());
+        }
+
+        Ok(Self {
+            command,
+            fsync,
+            backup,
+            keep_backups,
+            filters,
+            no_filters,
+            dry_run,
+            plan_file,
+            order,
+            clean_action,
+            yes,
+            keep,
+            link_roots,
+            cache_dir,
+            cache_limit,
+            targets,
+            release,
+            catalogs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_size_accepts_units() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("10K").unwrap(), 10 << 10);
+        assert_eq!(parse_size("200G").unwrap(), 200 << 30);
+        assert_eq!(parse_size(" 3 mib ").unwrap(), 3 << 20);
+        assert_eq!(parse_size("1TB").unwrap(), 1 << 40);
+    }
+
+    #[test]
+    fn parse_size_rejects_garbage() {
+        for value in ["", "G", "12X", "1.5G", "-1", "99999999999T"] {
+            assert!(parse_size(value).is_err(), "{:?} should be rejected", value);
+        }
+    }
+}
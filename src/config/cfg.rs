@@ -1,22 +1,145 @@
-#[derive(Clone)]
+use serde::{Deserialize, Serialize};
+use std::{fmt, path::PathBuf, str::FromStr};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ConfigSource {
+    pub url: String,
+    pub section: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PatchSource {
+    pub version: String,
+    pub index_url: String,
+    pub index_mirrors: Vec<String>,
+    pub zip_bases: Vec<String>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct Config {
     pub index_url: String,
+    pub index_mirrors: Vec<String>,
     pub zip_bases: Vec<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub source: Option<ConfigSource>,
+    #[serde(default)]
+    pub patches: Vec<PatchSource>,
+}
+
+impl Config {
+    pub fn is_predownload(&self) -> bool {
+        self.source
+            .as_ref()
+            .is_some_and(|source| source.section == "predownload")
+    }
+
+    pub fn patch_for(&self, installed_version: &str) -> Option<&PatchSource> {
+        self.patches
+            .iter()
+            .find(|patch| patch.version == installed_version)
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilterPatterns {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub voice_languages: Option<Vec<String>>,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DownloadOrder {
+    #[default]
+    Index,
+    SmallestFirst,
+    LargestFirst,
+    Directory,
+    CriticalFirst,
+}
+
+impl DownloadOrder {
+    pub const ALL: [DownloadOrder; 5] = [
+        DownloadOrder::Index,
+        DownloadOrder::SmallestFirst,
+        DownloadOrder::LargestFirst,
+        DownloadOrder::Directory,
+        DownloadOrder::CriticalFirst,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DownloadOrder::Index => "index",
+            DownloadOrder::SmallestFirst => "smallest-first",
+            DownloadOrder::LargestFirst => "largest-first",
+            DownloadOrder::Directory => "directory",
+            DownloadOrder::CriticalFirst => "critical-first",
+        }
+    }
+}
+
+impl fmt::Display for DownloadOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for DownloadOrder {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|order| order.as_str() == value)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::ALL.iter().map(|order| order.as_str()).collect();
+                format!(
+                    "Unknown download order {:?}, expected one of: {}",
+                    value,
+                    names.join(", ")
+                )
+            })
+    }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct DownloadOptions {
     pub concurrency: usize,
+    pub fsync: bool,
+    pub staging: bool,
+    pub backup: bool,
+    pub keep_backups: usize,
+    pub filters: FilterPatterns,
+    pub order: DownloadOrder,
+    pub link_roots: Vec<PathBuf>,
+    pub cache_dir: Option<PathBuf>,
+    pub cache_limit: Option<u64>,
 }
 
 impl Default for DownloadOptions {
     fn default() -> Self {
-        Self { concurrency: 4 }
+        Self {
+            concurrency: 4,
+            fsync: false,
+            staging: false,
+            backup: false,
+            keep_backups: 3,
+            filters: FilterPatterns::default(),
+            order: DownloadOrder::default(),
+            link_roots: Vec::new(),
+            cache_dir: None,
+            cache_limit: None,
+        }
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ResourceItem {
     pub dest: String,
     pub md5: Option<String>,
+    pub size: Option<u64>,
 }
@@ -0,0 +1,303 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::io::{
+    file::{resolve_dest, state_dir, write_atomic},
+    launcher::LAUNCHER_CONFIG_FILE,
+    logging::unix_timestamp,
+    snapshot::{InstallSnapshot, write_snapshot},
+};
+
+const BACKUP_DIR: &str = "backups";
+
+#[derive(Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub snapshot: InstallSnapshot,
+    pub replaced: Vec<String>,
+    pub added: Vec<String>,
+    pub created: u64,
+}
+
+pub struct Backup {
+    dir: PathBuf,
+    manifest: BackupManifest,
+}
+
+fn backups_root(folder: &Path) -> PathBuf {
+    state_dir(folder).join(BACKUP_DIR)
+}
+
+// Versions repeat (or are unknown) across updates, so the creation time keeps each backup apart.
+fn backup_name(snapshot: &InstallSnapshot, created: u64) -> String {
+    let version = snapshot.version.as_deref().unwrap_or("unknown");
+    format!("{}-{}", version, created)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn manifest_path(dir: &Path) -> PathBuf {
+    let mut name = dir.as_os_str().to_owned();
+    name.push(".json");
+    PathBuf::from(name)
+}
+
+fn move_file(from: &Path, to: &Path) -> Result<(), String> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    fs::rename(from, to).map_err(|e| {
+        format!(
+            "Failed to move {} to {}: {}",
+            from.display(),
+            to.display(),
+            e
+        )
+    })
+}
+
+fn fresh_dir(folder: &Path, previous: &InstallSnapshot, created: u64) -> Result<PathBuf, String> {
+    let root = backups_root(folder);
+    let name = backup_name(previous, created);
+
+    let mut dir = root.join(&name);
+    let mut attempt = 1;
+    while dir.exists() || manifest_path(&dir).exists() {
+        attempt += 1;
+        dir = root.join(format!("{}-{}", name, attempt));
+    }
+
+    fs::create_dir_all(&root).map_err(|e| format!("Failed to create backup folder: {}", e))?;
+    Ok(dir)
+}
+
+// The launcher metadata is rewritten for the new version, so rollback has to restore or drop it too.
+fn keep_launcher_config(
+    folder: &Path,
+    dir: &Path,
+    replaced: &mut Vec<String>,
+    added: &mut Vec<String>,
+) -> Result<(), String> {
+    let live = folder.join(LAUNCHER_CONFIG_FILE);
+    if !live.is_file() {
+        added.push(LAUNCHER_CONFIG_FILE.to_string());
+        return Ok(());
+    }
+
+    fs::copy(&live, dir.join(LAUNCHER_CONFIG_FILE))
+        .map_err(|e| format!("Failed to back up {}: {}", LAUNCHER_CONFIG_FILE, e))?;
+    replaced.push(LAUNCHER_CONFIG_FILE.to_string());
+    Ok(())
+}
+
+impl Backup {
+    pub fn adopt(
+        folder: &Path,
+        previous: &InstallSnapshot,
+        moved_dir: &Path,
+        mut replaced: Vec<String>,
+        mut added: Vec<String>,
+    ) -> Result<Self, String> {
+        let created = unix_timestamp();
+        let dir = fresh_dir(folder, previous, created)?;
+        let stored = if moved_dir.exists() {
+            fs::rename(moved_dir, &dir)
+        } else {
+            fs::create_dir_all(&dir)
+        };
+        stored.map_err(|e| format!("Failed to store backup {}: {}", dir.display(), e))?;
+        keep_launcher_config(folder, &dir, &mut replaced, &mut added)?;
+
+        let backup = Self {
+            dir,
+            manifest: BackupManifest {
+                snapshot: previous.clone(),
+                replaced,
+                added,
+                created,
+            },
+        };
+        backup.save()?;
+        Ok(backup)
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let json = serde_json::to_vec_pretty(&self.manifest)
+            .map_err(|e| format!("Failed to encode backup manifest: {}", e))?;
+        write_atomic(&manifest_path(&self.dir), &json)
+            .map_err(|e| format!("Failed to write backup manifest: {}", e))
+    }
+}
+
+fn list_backups(folder: &Path) -> Vec<(PathBuf, BackupManifest)> {
+    let Ok(entries) = fs::read_dir(backups_root(folder)) else {
+        return Vec::new();
+    };
+
+    let mut backups: Vec<(PathBuf, BackupManifest)> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .filter_map(|dir| {
+            let data = fs::read(manifest_path(&dir)).ok()?;
+            let manifest = serde_json::from_slice(&data).ok()?;
+            Some((dir, manifest))
+        })
+        .collect();
+
+    backups.sort_by(|(a_dir, a), (b_dir, b)| a.created.cmp(&b.created).then(a_dir.cmp(b_dir)));
+    backups
+}
+
+fn remove_backup(dir: &Path) -> Result<(), String> {
+    let removed = fs::remove_dir_all(dir).and_then(|_| match fs::remove_file(manifest_path(dir)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    });
+    removed.map_err(|e| format!("Failed to remove backup {}: {}", dir.display(), e))
+}
+
+pub fn prune_backups(folder: &Path, keep: usize) -> Result<usize, String> {
+    let backups = list_backups(folder);
+    let excess = backups.len().saturating_sub(keep);
+
+    for (dir, _) in backups.iter().take(excess) {
+        remove_backup(dir)?;
+    }
+
+    Ok(excess)
+}
+
+pub fn rollback(folder: &Path) -> Result<BackupManifest, String> {
+    let (dir, manifest) = list_backups(folder)
+        .pop()
+        .ok_or_else(|| format!("No backups found in {}", folder.display()))?;
+
+    for dest in &manifest.added {
+        let live = resolve_dest(folder, dest)?;
+        match fs::remove_file(&live) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(format!("Failed to remove {}: {}", live.display(), e));
+            }
+            _ => {}
+        }
+    }
+
+    for dest in &manifest.replaced {
+        let live = resolve_dest(folder, dest)?;
+        let saved = resolve_dest(&dir, dest)?;
+        if !saved.is_file() {
+            // Already restored by an earlier, interrupted rollback.
+            continue;
+        }
+        if live.is_file() {
+            fs::remove_file(&live)
+                .map_err(|e| format!("Failed to remove {}: {}", live.display(), e))?;
+        }
+        move_file(&saved, &live)?;
+    }
+
+    write_snapshot(folder, &manifest.snapshot)?;
+    remove_backup(&dir)?;
+
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(version: Option<&str>) -> InstallSnapshot {
+        InstallSnapshot {
+            version: version.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn backup_names_are_sanitized_and_timestamped() {
+        assert_eq!(backup_name(&snapshot(Some("2.1/os")), 7), "2.1_os-7");
+        assert_eq!(backup_name(&snapshot(None), 7), "unknown-7");
+    }
+
+    #[test]
+    fn backups_never_overwrite_each_other() {
+        let folder = tempfile::tempdir().unwrap();
+        let previous = snapshot(None);
+
+        let first = Backup::adopt(
+            folder.path(),
+            &previous,
+            Path::new("/nonexistent"),
+            vec![],
+            vec![],
+        )
+        .unwrap();
+        let second = Backup::adopt(
+            folder.path(),
+            &previous,
+            Path::new("/nonexistent"),
+            vec![],
+            vec![],
+        )
+        .unwrap();
+
+        assert_ne!(first.dir, second.dir);
+        assert_eq!(list_backups(folder.path()).len(), 2);
+        assert_eq!(prune_backups(folder.path(), 1).unwrap(), 1);
+        assert_eq!(list_backups(folder.path())[0].0, second.dir);
+    }
+
+    #[test]
+    fn rollback_restores_the_launcher_config() {
+        let folder = tempfile::tempdir().unwrap();
+        let launcher = folder.path().join(LAUNCHER_CONFIG_FILE);
+        fs::write(&launcher, r#"{"version":"1.0"}"#).unwrap();
+
+        Backup::adopt(
+            folder.path(),
+            &snapshot(Some("1.0")),
+            Path::new("/nonexistent"),
+            vec![],
+            vec![],
+        )
+        .unwrap();
+        fs::write(&launcher, r#"{"version":"2.0"}"#).unwrap();
+
+        let manifest = rollback(folder.path()).unwrap();
+        assert_eq!(manifest.replaced, [LAUNCHER_CONFIG_FILE]);
+        assert_eq!(
+            fs::read_to_string(&launcher).unwrap(),
+            r#"{"version":"1.0"}"#
+        );
+    }
+
+    #[test]
+    fn rollback_removes_a_launcher_config_the_update_created() {
+        let folder = tempfile::tempdir().unwrap();
+        let launcher = folder.path().join(LAUNCHER_CONFIG_FILE);
+
+        Backup::adopt(
+            folder.path(),
+            &snapshot(Some("1.0")),
+            Path::new("/nonexistent"),
+            vec![],
+            vec![],
+        )
+        .unwrap();
+        fs::write(&launcher, r#"{"version":"2.0"}"#).unwrap();
+
+        rollback(folder.path()).unwrap();
+        assert!(!launcher.exists());
+    }
+}
@@ -0,0 +1,163 @@
+use colored::Colorize;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::{cfg::ResourceItem, status::Status},
+    io::{
+        file::{LinkKind, calculate_md5, link_or_copy, resolve_dest},
+        journal::{FileState, SharedJournal, record_file},
+        logging::{SharedLogFile, log_error},
+        util::bytes_to_human,
+    },
+};
+
+#[derive(Default)]
+pub struct LinkSummary {
+    pub reflinked: usize,
+    pub hardlinked: usize,
+    pub copied: usize,
+    pub bytes: u64,
+}
+
+impl LinkSummary {
+    pub fn total(&self) -> usize {
+        self.reflinked + self.hardlinked + self.copied
+    }
+}
+
+async fn find_source(candidates: Vec<PathBuf>, item: &ResourceItem) -> Option<PathBuf> {
+    let (Some(md5), Some(size)) = (item.md5.as_deref(), item.size) else {
+        return None;
+    };
+
+    for candidate in candidates {
+        if !fs::metadata(&candidate).is_ok_and(|m| m.is_file() && m.len() == size) {
+            continue;
+        }
+        if calculate_md5(&candidate).await.ok().as_deref() == Some(md5) {
+            return Some(candidate);
+        }
+    }
+
+    None
+}
+
+async fn link_matching<F>(
+    resources: Vec<ResourceItem>,
+    target: &Path,
+    candidates: F,
+    journal: &SharedJournal,
+    log_file: &SharedLogFile,
+) -> (Vec<ResourceItem>, LinkSummary)
+where
+    F: Fn(&ResourceItem) -> Vec<PathBuf>,
+{
+    let mut summary = LinkSummary::default();
+    let mut remaining = Vec::with_capacity(resources.len());
+
+    for item in resources {
+        let Ok(path) = resolve_dest(target, &item.dest) else {
+            remaining.push(item);
+            continue;
+        };
+
+        // Files already here are verified by the normal download pass.
+        if fs::metadata(&path).is_ok_and(|m| Some(m.len()) == item.size) {
+            remaining.push(item);
+            continue;
+        }
+
+        let Some(source) = find_source(candidates(&item), &item).await else {
+            remaining.push(item);
+            continue;
+        };
+
+        let dest = path.clone();
+        let linked = tokio::task::spawn_blocking(move || link_or_copy(&source, &dest))
+            .await
+            .map_err(|e| format!("Failed to join link task: {}", e))
+            .and_then(|result| result);
+        match linked {
+            Ok(kind) => {
+                match kind {
+                    LinkKind::Reflink => summary.reflinked += 1,
+                    LinkKind::Hardlink => summary.hardlinked += 1,
+                    LinkKind::Copy => summary.copied += 1,
+                }
+                summary.bytes += item.size.unwrap_or_default();
+                if let Err(e) = record_file(journal, &item.dest, FileState::Verified) {
+                    log_error(log_file, &e);
+                }
+            }
+            Err(e) => {
+                log_error(log_file, &e);
+                remaining.push(item);
+            }
+        }
+    }
+
+    (remaining, summary)
+}
+
+pub async fn link_from_roots(
+    resources: Vec<ResourceItem>,
+    target: &Path,
+    roots: &[PathBuf],
+    journal: &SharedJournal,
+    log_file: &SharedLogFile,
+) -> (Vec<ResourceItem>, LinkSummary) {
+    let roots: Vec<PathBuf> = roots
+        .iter()
+        .filter(|root| fs::canonicalize(root).ok() != fs::canonicalize(target).ok())
+        .cloned()
+        .collect();
+
+    println!(
+        "{} Looking for identical files in {} other installs...",
+        Status::info(),
+        roots.len().to_string().cyan()
+    );
+
+    let candidates = |item: &ResourceItem| {
+        roots
+            .iter()
+            .filter_map(|root| resolve_dest(root, &item.dest).ok())
+            .collect()
+    };
+    link_matching(resources, target, candidates, journal, log_file).await
+}
+
+// Files fetched for an earlier target in the same run, keyed by MD5.
+pub async fn link_known_files(
+    resources: Vec<ResourceItem>,
+    target: &Path,
+    known: &HashMap<String, PathBuf>,
+    journal: &SharedJournal,
+    log_file: &SharedLogFile,
+) -> (Vec<ResourceItem>, LinkSummary) {
+    let candidates = |item: &ResourceItem| {
+        item.md5
+            .as_deref()
+            .and_then(|md5| known.get(md5))
+            .cloned()
+            .into_iter()
+            .collect()
+    };
+    link_matching(resources, target, candidates, journal, log_file).await
+}
+
+pub fn print_link_summary(summary: &LinkSummary) {
+    println!(
+        "{} Reused {} files ({}) from other installs: {} reflinked, {} hardlinked, {} copied",
+        Status::success(),
+        summary.total().to_string().green(),
+        bytes_to_human(summary.bytes).green(),
+        summary.reflinked,
+        summary.hardlinked,
+        summary.copied
+    );
+}
@@ -0,0 +1,233 @@
+use colored::Colorize;
+use glob::{MatchOptions, Pattern};
+
+use crate::{
+    config::{
+        cfg::{FilterPatterns, ResourceItem},
+        status::Status,
+    },
+    io::{
+        file::get_filename,
+        snapshot::{InstallSnapshot, InstalledFile},
+        util::bytes_to_human,
+    },
+};
+
+use super::voice::{voice_language, wants_all};
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+pub struct PatternSet(Vec<Pattern>);
+
+impl PatternSet {
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Result<Self, String> {
+        patterns
+            .iter()
+            .map(|pattern| {
+                let pattern = pattern.as_ref();
+                Pattern::new(pattern).map_err(|e| format!("Invalid pattern {:?}: {}", pattern, e))
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    // Patterns without a `/` match the file name anywhere in the tree, like .gitignore.
+    pub fn matches(&self, dest: &str) -> bool {
+        self.0.iter().any(|pattern| {
+            if pattern.as_str().contains('/') {
+                pattern.matches_with(dest, MATCH_OPTIONS)
+            } else {
+                pattern.matches_with(&get_filename(dest), MATCH_OPTIONS)
+            }
+        })
+    }
+}
+
+pub struct ResourceFilter {
+    include: PatternSet,
+    exclude: PatternSet,
+    voice_languages: Option<Vec<String>>,
+}
+
+impl ResourceFilter {
+    pub fn new(patterns: &FilterPatterns) -> Result<Self, String> {
+        Ok(Self {
+            include: PatternSet::new(&patterns.include)?,
+            exclude: PatternSet::new(&patterns.exclude)?,
+            voice_languages: patterns
+                .voice_languages
+                .clone()
+                .filter(|languages| !wants_all(languages)),
+        })
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.include.is_empty() || !self.exclude.is_empty() || self.voice_languages.is_some()
+    }
+
+    pub fn matches(&self, dest: &str) -> bool {
+        (self.include.is_empty() || self.include.matches(dest))
+            && !self.exclude.matches(dest)
+            && self.wants_voice(dest)
+    }
+
+    fn wants_voice(&self, dest: &str) -> bool {
+        match (&self.voice_languages, voice_language(dest)) {
+            (Some(languages), Some(language)) => languages.iter().any(|l| l == language),
+            _ => true,
+        }
+    }
+
+    pub fn apply(&self, resources: Vec<ResourceItem>) -> (Vec<ResourceItem>, Vec<ResourceItem>) {
+        resources
+            .into_iter()
+            .partition(|item| self.matches(&item.dest))
+    }
+
+    // Files outside the filter were not looked at, so they are neither updated nor removed.
+    pub fn scope(&self, snapshot: &InstallSnapshot) -> InstallSnapshot {
+        InstallSnapshot {
+            files: snapshot
+                .files
+                .iter()
+                .filter(|file| self.matches(&file.dest))
+                .cloned()
+                .collect(),
+            ..snapshot.clone()
+        }
+    }
+
+    pub fn carry_over(&self, previous: &InstallSnapshot, snapshot: &mut InstallSnapshot) {
+        let skipped: Vec<InstalledFile> = previous
+            .files
+            .iter()
+            .filter(|file| !self.matches(&file.dest))
+            .cloned()
+            .collect();
+        snapshot.files.extend(skipped);
+    }
+}
+
+// Patterns from the last run apply again unless the command line brings its own.
+pub fn remember_patterns(filters: &mut FilterPatterns, snapshot: &InstallSnapshot) -> bool {
+    let remembered = !snapshot.include.is_empty() || !snapshot.exclude.is_empty();
+    if !remembered || !filters.include.is_empty() || !filters.exclude.is_empty() {
+        return false;
+    }
+
+    filters.include = snapshot.include.clone();
+    filters.exclude = snapshot.exclude.clone();
+    true
+}
+
+pub fn record_patterns(filters: &FilterPatterns, snapshot: &mut InstallSnapshot) {
+    snapshot.voice_languages = filters.voice_languages.clone();
+    snapshot.include = filters.include.clone();
+    snapshot.exclude = filters.exclude.clone();
+}
+
+pub fn print_filter_summary(filtered: &[ResourceItem]) {
+    let bytes: u64 = filtered.iter().filter_map(|item| item.size).sum();
+    let unknown = filtered.iter().filter(|item| item.size.is_none()).count();
+
+    let mut summary = format!(
+        "{} Filters skipped {} files ({})",
+        Status::info(),
+        filtered.len().to_string().yellow(),
+        bytes_to_human(bytes).yellow()
+    );
+    if unknown > 0 {
+        summary.push_str(&format!(", {} of unknown size", unknown));
+    }
+    println!("{}", summary);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(include: &[&str], exclude: &[&str], voice: Option<&[&str]>) -> ResourceFilter {
+        let strings = |list: &[&str]| list.iter().map(|s| s.to_string()).collect();
+        ResourceFilter::new(&FilterPatterns {
+            include: strings(include),
+            exclude: strings(exclude),
+            voice_languages: voice.map(strings),
+        })
+        .unwrap()
+    }
+
+    fn snapshot(include: &[&str], exclude: &[&str]) -> InstallSnapshot {
+        InstallSnapshot {
+            include: include.iter().map(|s| s.to_string()).collect(),
+            exclude: exclude.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn patterns_without_a_slash_match_file_names_anywhere() {
+        let patterns = PatternSet::new(&["*.mp4"]).unwrap();
+        assert!(patterns.matches("Client/Content/Movies/intro.mp4"));
+        assert!(!patterns.matches("Client/Content/Movies/intro.mp4.bak"));
+    }
+
+    #[test]
+    fn patterns_with_a_slash_match_the_whole_path() {
+        let patterns = PatternSet::new(&["Client/Binaries/**"]).unwrap();
+        assert!(patterns.matches("Client/Binaries/Win64/Client.exe"));
+        assert!(!patterns.matches("Other/Client/Binaries/a.dll"));
+
+        let single = PatternSet::new(&["Client/*.pak"]).unwrap();
+        assert!(single.matches("Client/a.pak"));
+        assert!(!single.matches("Client/Paks/a.pak"));
+    }
+
+    #[test]
+    fn invalid_patterns_are_reported() {
+        assert!(PatternSet::new(&["[a"]).is_err());
+    }
+
+    #[test]
+    fn excludes_win_over_includes() {
+        let filter = filter(&["Client/**"], &["*.mp4"], None);
+        assert!(filter.matches("Client/a.pak"));
+        assert!(!filter.matches("Client/intro.mp4"));
+        assert!(!filter.matches("Engine/a.pak"));
+    }
+
+    #[test]
+    fn voice_filter_only_touches_voice_folders() {
+        let filter = filter(&[], &[], Some(&["ja"]));
+        let media = "Client/Content/Aki/WwiseAudio_Generated/Media";
+
+        assert!(filter.matches(&format!("{}/ja/a.wem", media)));
+        assert!(!filter.matches(&format!("{}/en/a.wem", media)));
+        assert!(filter.matches("Client/en/readme.txt"));
+    }
+
+    #[test]
+    fn saved_patterns_apply_unless_new_ones_are_given() {
+        let saved = snapshot(&["Client/**"], &["*.mp4"]);
+
+        let mut filters = FilterPatterns::default();
+        assert!(remember_patterns(&mut filters, &saved));
+        assert_eq!(filters.include, ["Client/**"]);
+        assert_eq!(filters.exclude, ["*.mp4"]);
+
+        let mut filters = FilterPatterns {
+            exclude: vec!["*.bk2".to_string()],
+            ..Default::default()
+        };
+        assert!(!remember_patterns(&mut filters, &saved));
+        assert!(filters.include.is_empty());
+        assert_eq!(filters.exclude, ["*.bk2"]);
+    }
+}
@@ -1 +1,11 @@
+pub mod backup;
+pub mod dedupe;
+pub mod filter;
+pub mod order;
+pub mod patch;
+pub mod plan;
 pub mod progress;
+pub mod staging;
+pub mod stray;
+pub mod update;
+pub mod voice;
@@ -0,0 +1,49 @@
+use std::{collections::HashMap, path::Path};
+
+use crate::config::cfg::{DownloadOrder, ResourceItem};
+
+const CRITICAL_EXTENSIONS: [&str; 7] = ["exe", "dll", "json", "ini", "xml", "txt", "bat"];
+const BULK_EXTENSIONS: [&str; 5] = ["pak", "ucas", "utoc", "pck", "wem"];
+
+fn critical_rank(dest: &str) -> u8 {
+    let extension = Path::new(dest)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    if CRITICAL_EXTENSIONS.contains(&extension.as_str()) {
+        0
+    } else if BULK_EXTENSIONS.contains(&extension.as_str()) {
+        2
+    } else {
+        1
+    }
+}
+
+fn split_dir(dest: &str) -> (&str, &str) {
+    dest.rsplit_once('/').unwrap_or(("", dest))
+}
+
+pub fn order_resources(
+    resources: &mut [ResourceItem],
+    order: DownloadOrder,
+    size_hints: &HashMap<String, u64>,
+) {
+    // Unknown sizes sort as zero; the sorts are stable, so ties keep index order.
+    let size = |item: &ResourceItem| {
+        item.size
+            .or_else(|| size_hints.get(&item.dest).copied())
+            .unwrap_or_default()
+    };
+
+    match order {
+        DownloadOrder::Index => {}
+        DownloadOrder::SmallestFirst => resources.sort_by_key(size),
+        DownloadOrder::LargestFirst => resources.sort_by_key(|item| std::cmp::Reverse(size(item))),
+        DownloadOrder::Directory => {
+            resources.sort_by(|a, b| split_dir(&a.dest).cmp(&split_dir(&b.dest)))
+        }
+        DownloadOrder::CriticalFirst => resources.sort_by_key(|item| critical_rank(&item.dest)),
+    }
+}
@@ -0,0 +1,242 @@
+use colored::Colorize;
+use indicatif::ProgressBar;
+use reqwest::Client;
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::{
+    config::{
+        cfg::{Config, DownloadOptions, PatchSource, ResourceItem},
+        status::Status,
+    },
+    io::{
+        file::{calculate_md5, get_filename, resolve_dest, state_dir},
+        logging::{SharedLogFile, log_error},
+        util::{parse_resources, track_progress},
+    },
+    network::client::{download_file, fetch_index},
+};
+
+const DIFF_EXTENSIONS: [&str; 2] = [".krdiff", ".hdiff"];
+
+#[cfg(windows)]
+const PATCHER_BINARY: &str = "hpatchz.exe";
+#[cfg(not(windows))]
+const PATCHER_BINARY: &str = "hpatchz";
+
+// A binary that cannot be started (wrong architecture, no execute bit) counts as missing.
+fn find_patcher() -> Option<PathBuf> {
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(PATCHER_BINARY))
+        .filter(|candidate| candidate.is_file())
+        .find(|candidate| Command::new(candidate).arg("-v").output().is_ok())
+}
+
+fn diff_target(dest: &str) -> Option<&str> {
+    DIFF_EXTENSIONS
+        .iter()
+        .find_map(|ext| dest.strip_suffix(ext))
+}
+
+async fn run_patcher(patcher: &Path, old: &Path, diff: &Path, out: &Path) -> Result<(), String> {
+    let (patcher, old, diff, out) = (
+        patcher.to_path_buf(),
+        old.to_path_buf(),
+        diff.to_path_buf(),
+        out.to_path_buf(),
+    );
+
+    let status = tokio::task::spawn_blocking(move || {
+        Command::new(patcher)
+            .arg("-f")
+            .arg(old)
+            .arg(diff)
+            .arg(out)
+            .status()
+    })
+    .await
+    .map_err(|e| format!("Failed to join patcher task: {}", e))?
+    .map_err(|e| format!("Failed to run patcher: {}", e))?;
+
+    if !status.success() {
+        return Err(format!("Patcher exited with {}", status));
+    }
+
+    Ok(())
+}
+
+async fn apply_diff(
+    patcher: &Path,
+    folder: &Path,
+    originals: &Path,
+    staging: &Path,
+    diff: &ResourceItem,
+    target: &ResourceItem,
+) -> Result<(), String> {
+    let old_path = resolve_dest(originals, &target.dest)?;
+    let live_path = resolve_dest(folder, &target.dest)?;
+    let diff_path = resolve_dest(staging, &diff.dest)?;
+
+    let mut out_name = live_path.as_os_str().to_owned();
+    out_name.push(".patched");
+    let out_path = PathBuf::from(out_name);
+
+    let result = async {
+        if let Some(parent) = out_path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        run_patcher(patcher, &old_path, &diff_path, &out_path).await?;
+
+        let actual = calculate_md5(&out_path).await?;
+        if target.md5.as_deref() != Some(actual.as_str()) {
+            return Err(format!(
+                "Patched {} has checksum {}, expected {}",
+                target.dest,
+                actual,
+                target.md5.as_deref().unwrap_or("unknown")
+            ));
+        }
+
+        tokio::fs::rename(&out_path, &live_path)
+            .await
+            .map_err(|e| format!("Failed to replace {}: {}", target.dest, e))
+    }
+    .await;
+
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(&out_path).await;
+    }
+
+    result
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn apply_patch(
+    client: &Client,
+    patch: &PatchSource,
+    to_download: Vec<ResourceItem>,
+    folder: &Path,
+    originals: &Path,
+    options: &DownloadOptions,
+    should_stop: &AtomicBool,
+    log_file: &SharedLogFile,
+) -> Vec<ResourceItem> {
+    let Some(patcher) = find_patcher() else {
+        println!(
+            "{} Patch from {} available, but no working {} was found in PATH; downloading full files",
+            Status::warning(),
+            patch.version.yellow(),
+            PATCHER_BINARY
+        );
+        return to_download;
+    };
+
+    println!(
+        "{} Fetching patch index for {}...",
+        Status::info(),
+        patch.version.cyan()
+    );
+
+    let patch_config = Config {
+        index_url: patch.index_url.clone(),
+        index_mirrors: patch.index_mirrors.clone(),
+        zip_bases: patch.zip_bases.clone(),
+        ..Default::default()
+    };
+    let patch_items = match fetch_index(client, &patch_config, log_file)
+        .await
+        .and_then(|data| parse_resources(&data))
+    {
+        Ok(index) => index.resources,
+        Err(e) => {
+            log_error(log_file, &format!("Patch index unavailable: {}", e));
+            println!(
+                "{} Patch index unavailable ({}), downloading full files",
+                Status::warning(),
+                e
+            );
+            return to_download;
+        }
+    };
+
+    let staging = state_dir(folder).join("patch").join(&patch.version);
+    let (_, _, progress) = track_progress(0);
+    let bar = ProgressBar::hidden();
+
+    let by_dest: HashMap<&str, &ResourceItem> = to_download
+        .iter()
+        .map(|item| (item.dest.as_str(), item))
+        .collect();
+    let mut patched = HashSet::new();
+    for diff in &patch_items {
+        if should_stop.load(Ordering::SeqCst) {
+            break;
+        }
+
+        let Some(&target) = diff_target(&diff.dest).and_then(|dest| by_dest.get(dest)) else {
+            continue;
+        };
+
+        if target.md5.is_none()
+            || !resolve_dest(originals, &target.dest).is_ok_and(|path| path.is_file())
+        {
+            continue;
+        }
+
+        println!(
+            "{} Patching {}",
+            Status::progress(),
+            get_filename(&target.dest).cyan()
+        );
+
+        let downloaded = download_file(
+            client,
+            &patch_config,
+            &diff.dest,
+            &staging,
+            diff.md5.as_deref(),
+            diff.size,
+            options,
+            log_file,
+            should_stop,
+            &progress,
+            &bar,
+            &bar,
+        )
+        .await;
+
+        if !downloaded {
+            continue;
+        }
+
+        match apply_diff(&patcher, folder, originals, &staging, diff, target).await {
+            Ok(()) => {
+                patched.insert(target.dest.clone());
+            }
+            Err(e) => {
+                log_error(log_file, &format!("Patch failed, using full file: {}", e));
+            }
+        }
+    }
+
+    let _ = tokio::fs::remove_dir_all(&staging).await;
+
+    println!(
+        "{} Patched {} files, {} files need a full download",
+        Status::success(),
+        patched.len().to_string().green(),
+        (to_download.len() - patched.len()).to_string().cyan()
+    );
+
+    to_download
+        .into_iter()
+        .filter(|item| !patched.contains(&item.dest))
+        .collect()
+}
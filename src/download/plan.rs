@@ -0,0 +1,207 @@
+use colored::Colorize;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    config::{
+        cfg::{Config, DownloadOrder, ResourceItem},
+        status::Status,
+    },
+    io::{
+        file::{check_existing_file, file_size, resolve_dest, write_atomic},
+        util::bytes_to_human,
+    },
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlannedAction {
+    Download,
+    Resume,
+    Skip,
+}
+
+#[derive(Serialize)]
+pub struct PlannedFile {
+    pub dest: String,
+    pub path: PathBuf,
+    pub action: PlannedAction,
+    pub size: Option<u64>,
+    pub present: u64,
+    pub md5: Option<String>,
+}
+
+#[derive(Default, Serialize)]
+pub struct PlanTotals {
+    pub files: usize,
+    pub bytes: u64,
+    pub unknown_size: usize,
+}
+
+impl PlanTotals {
+    fn add(&mut self, bytes: Option<u64>) {
+        self.files += 1;
+        match bytes {
+            Some(bytes) => self.bytes += bytes,
+            None => self.unknown_size += 1,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct DownloadPlan {
+    pub version: Option<String>,
+    pub index_url: String,
+    pub target: PathBuf,
+    pub cdns: Vec<String>,
+    pub order: DownloadOrder,
+    pub download: PlanTotals,
+    pub resume: PlanTotals,
+    pub skip: PlanTotals,
+    pub filtered: PlanTotals,
+    pub files: Vec<PlannedFile>,
+}
+
+async fn scan_file(item: &ResourceItem, path: &Path) -> (PlannedAction, u64) {
+    let present = file_size(path).await;
+
+    // Mirrors download_file: only entries with both checksum and size can be skipped.
+    if item.md5.is_some()
+        && item.size.is_some()
+        && check_existing_file(path, item.md5.as_deref(), item.size).await
+    {
+        return (PlannedAction::Skip, present);
+    }
+
+    match item.size {
+        Some(size) if present > 0 && present < size => (PlannedAction::Resume, present),
+        _ => (PlannedAction::Download, present),
+    }
+}
+
+pub async fn build_plan(
+    config: &Config,
+    resources: &[ResourceItem],
+    filtered: &[ResourceItem],
+    target: &Path,
+    order: DownloadOrder,
+) -> DownloadPlan {
+    println!(
+        "{} Scanning {} local files...",
+        Status::info(),
+        resources.len().to_string().cyan()
+    );
+
+    let mut plan = DownloadPlan {
+        version: config.version.clone(),
+        index_url: config.index_url.clone(),
+        target: target.to_path_buf(),
+        cdns: config.zip_bases.clone(),
+        order,
+        download: PlanTotals::default(),
+        resume: PlanTotals::default(),
+        skip: PlanTotals::default(),
+        filtered: PlanTotals::default(),
+        files: Vec::with_capacity(resources.len()),
+    };
+
+    for item in filtered {
+        plan.filtered.add(item.size);
+    }
+
+    for item in resources {
+        let Ok(path) = resolve_dest(target, &item.dest) else {
+            continue;
+        };
+        let (action, present) = scan_file(item, &path).await;
+
+        match action {
+            PlannedAction::Download => plan.download.add(item.size),
+            PlannedAction::Resume => plan
+                .resume
+                .add(item.size.map(|size| size.saturating_sub(present))),
+            PlannedAction::Skip => plan.skip.add(item.size),
+        }
+
+        plan.files.push(PlannedFile {
+            dest: item.dest.clone(),
+            path,
+            action,
+            size: item.size,
+            present,
+            md5: item.md5.clone(),
+        });
+    }
+
+    plan
+}
+
+fn print_totals(label: &str, totals: &PlanTotals) {
+    let mut line = format!(
+        "{} {}: {} files, {}",
+        Status::info(),
+        label,
+        totals.files.to_string().cyan(),
+        bytes_to_human(totals.bytes).cyan()
+    );
+    if totals.unknown_size > 0 {
+        line.push_str(&format!(" ({} of unknown size)", totals.unknown_size));
+    }
+    println!("{}", line);
+}
+
+pub fn print_plan(plan: &DownloadPlan) {
+    println!(
+        "\n{} Dry run for version {}, nothing will be written",
+        Status::info(),
+        plan.version.as_deref().unwrap_or("unknown").cyan()
+    );
+    println!(
+        "{} Target: {}",
+        Status::info(),
+        plan.target.display().to_string().cyan()
+    );
+    println!(
+        "{} Download order: {}",
+        Status::info(),
+        plan.order.to_string().cyan()
+    );
+    for (i, cdn) in plan.cdns.iter().enumerate() {
+        println!("{} CDN {}: {}", Status::info(), i + 1, cdn);
+    }
+
+    for file in plan
+        .files
+        .iter()
+        .filter(|file| file.action != PlannedAction::Skip)
+    {
+        let action = match file.action {
+            PlannedAction::Resume => "resume".yellow(),
+            _ => "download".green(),
+        };
+        let size = file
+            .size
+            .map(bytes_to_human)
+            .unwrap_or_else(|| "unknown size".to_string());
+        println!(
+            "{} {} {} ({})",
+            Status::progress(),
+            action,
+            file.path.display(),
+            size
+        );
+    }
+
+    print_totals("Download", &plan.download);
+    print_totals("Resume (remaining)", &plan.resume);
+    print_totals("Skip as valid", &plan.skip);
+    if plan.filtered.files > 0 {
+        print_totals("Filtered out", &plan.filtered);
+    }
+}
+
+pub fn write_plan(path: &Path, plan: &DownloadPlan) -> Result<(), String> {
+    let json =
+        serde_json::to_vec_pretty(plan).map_err(|e| format!("Failed to encode plan: {}", e))?;
+    write_atomic(path, &json).map_err(|e| format!("Failed to write plan {}: {}", path.display(), e))
+}
@@ -3,9 +3,9 @@ use std::{
     collections::VecDeque,
     sync::{
         Arc,
-        atomic::{AtomicU64, Ordering},
+        atomic::{AtomicBool, AtomicU64, Ordering},
     },
-    time::Instant,
+    time::{Duration, Instant},
 };
 use tokio::sync::{Mutex, Notify};
 
@@ -13,6 +13,7 @@ use tokio::sync::{Mutex, Notify};
 pub struct DownloadProgress {
     pub total_bytes: Arc<AtomicU64>,
     pub downloaded_bytes: Arc<AtomicU64>,
+    pub paused: Arc<AtomicBool>,
     pub start_time: Instant,
 }
 
@@ -20,6 +21,12 @@ impl DownloadProgress {
     pub fn downloaded(&self) -> u64 {
         self.downloaded_bytes.load(Ordering::SeqCst)
     }
+
+    pub async fn wait_while_paused(&self, should_stop: &AtomicBool) {
+        while self.paused.load(Ordering::SeqCst) && !should_stop.load(Ordering::SeqCst) {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -92,7 +99,7 @@ impl ProgressDisplay {
         total_bar
             .set_style(
                 ProgressStyle::default_bar()
-                    .template("{spinner:.green} [TOTAL] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({eta}, {binary_bytes_per_sec})")
+                    .template("{spinner:.green} [TOTAL] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({eta}, {binary_bytes_per_sec}) {msg}")
                     .unwrap()
                     .progress_chars("#>-"),
             );
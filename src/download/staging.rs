@@ -0,0 +1,391 @@
+use colored::Colorize;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::{
+        cfg::{Config, FilterPatterns, ResourceItem},
+        status::Status,
+    },
+    download::{
+        backup::{Backup, prune_backups},
+        filter::{ResourceFilter, record_patterns},
+    },
+    io::{
+        file::{calculate_md5, resolve_dest, state_dir, write_atomic},
+        launcher::write_launcher_config,
+        logging::{SharedLogFile, log_error, unix_timestamp},
+        snapshot::{InstallSnapshot, build_snapshot, load_snapshot, write_snapshot},
+        util::confirm,
+    },
+    network::client::{config_version, fetch_json},
+};
+
+const STAGING_DIR: &str = "staging";
+const UPDATE_DIR: &str = "update";
+const APPLY_BACKUP_DIR: &str = "apply-backup";
+const MANIFEST_FILE: &str = "staged.json";
+
+#[derive(Serialize, Deserialize)]
+pub struct StagedManifest {
+    pub config: Config,
+    pub files: Vec<ResourceItem>,
+    pub staged: Vec<String>,
+    pub written: u64,
+    #[serde(default)]
+    pub filters: FilterPatterns,
+}
+
+pub fn staging_dir(folder: &Path) -> PathBuf {
+    state_dir(folder).join(STAGING_DIR)
+}
+
+// Updates with backups land here first, so the live install stays playable until the swap.
+pub fn update_dir(folder: &Path) -> PathBuf {
+    state_dir(folder).join(UPDATE_DIR)
+}
+
+fn manifest_path(folder: &Path) -> PathBuf {
+    state_dir(folder).join(MANIFEST_FILE)
+}
+
+pub fn write_staged_manifest(
+    folder: &Path,
+    config: &Config,
+    files: &[ResourceItem],
+    filters: &FilterPatterns,
+) -> Result<StagedManifest, String> {
+    let staging = staging_dir(folder);
+    let staged = files
+        .iter()
+        .filter(|item| resolve_dest(&staging, &item.dest).is_ok_and(|path| path.is_file()))
+        .map(|item| item.dest.clone())
+        .collect();
+
+    let manifest = StagedManifest {
+        config: config.clone(),
+        files: files.to_vec(),
+        staged,
+        written: unix_timestamp(),
+        filters: filters.clone(),
+    };
+
+    let json = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| format!("Failed to encode staged manifest: {}", e))?;
+    write_atomic(&manifest_path(folder), &json)
+        .map_err(|e| format!("Failed to write staged manifest: {}", e))?;
+
+    Ok(manifest)
+}
+
+pub fn load_staged_manifest(folder: &Path) -> Result<Option<StagedManifest>, String> {
+    let path = manifest_path(folder);
+    let data = match fs::read(&path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+
+    serde_json::from_slice(&data)
+        .map(Some)
+        .map_err(|e| format!("Invalid staged manifest {}: {}", path.display(), e))
+}
+
+// A staging area left behind by a different version must not leak stale files into this one.
+pub fn prepare_staging(folder: &Path, version: Option<&str>) -> Result<PathBuf, String> {
+    let staging = staging_dir(folder);
+
+    let same_version = load_staged_manifest(folder)
+        .ok()
+        .flatten()
+        .is_some_and(|manifest| manifest.config.version.as_deref() == version);
+    if !same_version && staging.exists() {
+        fs::remove_dir_all(&staging)
+            .map_err(|e| format!("Failed to clear old staging area: {}", e))?;
+        let _ = fs::remove_file(manifest_path(folder));
+    }
+
+    fs::create_dir_all(&staging).map_err(|e| format!("Failed to create staging area: {}", e))?;
+    Ok(staging)
+}
+
+async fn verify_staged(folder: &Path, manifest: &StagedManifest) -> Result<(), String> {
+    let staging = staging_dir(folder);
+    let staged: HashSet<&str> = manifest.staged.iter().map(String::as_str).collect();
+
+    for item in manifest
+        .files
+        .iter()
+        .filter(|item| staged.contains(item.dest.as_str()))
+    {
+        let path = resolve_dest(&staging, &item.dest)?;
+        if let Some(expected) = item.md5.as_deref() {
+            let actual = calculate_md5(&path).await?;
+            if actual != expected {
+                return Err(format!(
+                    "Staged file {} is corrupt: expected {}, got {}",
+                    item.dest, expected, actual
+                ));
+            }
+        } else if !path.is_file() {
+            return Err(format!("Staged file {} is missing", item.dest));
+        }
+    }
+
+    Ok(())
+}
+
+struct Swap {
+    moved_out: Vec<(PathBuf, PathBuf)>,
+    moved_in: Vec<(PathBuf, PathBuf)>,
+}
+
+impl Swap {
+    fn move_file(from: &Path, to: &Path) -> Result<(), String> {
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        fs::rename(from, to).map_err(|e| {
+            format!(
+                "Failed to move {} to {}: {}",
+                from.display(),
+                to.display(),
+                e
+            )
+        })
+    }
+
+    fn retire(&mut self, live: PathBuf, backup: PathBuf) -> Result<bool, String> {
+        if !live.is_file() {
+            return Ok(false);
+        }
+
+        Self::move_file(&live, &backup)?;
+        self.moved_out.push((live, backup));
+        Ok(true)
+    }
+
+    fn install(&mut self, staged: PathBuf, live: PathBuf) -> Result<(), String> {
+        Self::move_file(&staged, &live)?;
+        self.moved_in.push((staged, live));
+        Ok(())
+    }
+
+    fn rollback(self) {
+        for (staged, live) in self.moved_in.into_iter().rev() {
+            let _ = fs::rename(&live, &staged);
+        }
+        for (live, backup) in self.moved_out.into_iter().rev() {
+            let _ = fs::rename(&backup, &live);
+        }
+    }
+}
+
+fn swap_in(
+    folder: &Path,
+    staging: &Path,
+    staged: &[String],
+    removed: &[String],
+    backup: &Path,
+) -> Result<Vec<String>, String> {
+    let mut swap = Swap {
+        moved_out: Vec::new(),
+        moved_in: Vec::new(),
+    };
+    let mut retired = Vec::new();
+
+    let result = (|| {
+        for dest in staged {
+            let live = resolve_dest(folder, dest)?;
+            if swap.retire(live.clone(), resolve_dest(backup, dest)?)? {
+                retired.push(dest.clone());
+            }
+            swap.install(resolve_dest(staging, dest)?, live)?;
+        }
+        for dest in removed {
+            if swap.retire(resolve_dest(folder, dest)?, resolve_dest(backup, dest)?)? {
+                retired.push(dest.clone());
+            }
+        }
+        Ok(())
+    })();
+
+    if result.is_err() {
+        swap.rollback();
+    }
+
+    result.map(|_| retired)
+}
+
+// Replaced and removed files become a rollback point when backups are kept, otherwise they are dropped.
+fn swap_with_backup(
+    folder: &Path,
+    staging: &Path,
+    staged: &[String],
+    removed: &[String],
+    previous: Option<&InstallSnapshot>,
+    keep_backups: Option<usize>,
+    log_file: &SharedLogFile,
+) -> Result<(), String> {
+    let backup = state_dir(folder).join(APPLY_BACKUP_DIR);
+    let _ = fs::remove_dir_all(&backup);
+
+    let retired = swap_in(folder, staging, staged, removed, &backup)?;
+
+    if let (Some(keep), Some(previous)) = (keep_backups, previous) {
+        let old_files: HashSet<&str> = previous.files.iter().map(|f| f.dest.as_str()).collect();
+        let added = staged
+            .iter()
+            .filter(|dest| !old_files.contains(dest.as_str()))
+            .cloned()
+            .collect();
+
+        let stored = Backup::adopt(folder, previous, &backup, retired, added)
+            .and_then(|_| prune_backups(folder, keep.max(1)));
+        if let Err(e) = stored {
+            log_error(log_file, &e);
+            println!("{} {}", Status::warning(), e);
+        }
+    }
+
+    let _ = fs::remove_dir_all(&backup);
+    Ok(())
+}
+
+pub fn prepare_update(folder: &Path) -> Result<PathBuf, String> {
+    let dir = update_dir(folder);
+    if dir.exists() {
+        fs::remove_dir_all(&dir).map_err(|e| format!("Failed to clear old update area: {}", e))?;
+    }
+
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create update area: {}", e))?;
+    Ok(dir)
+}
+
+// Every planned file was downloaded and verified into the update area; move them live in one go.
+pub fn apply_update(
+    folder: &Path,
+    previous: &InstallSnapshot,
+    files: &[ResourceItem],
+    removed: &[String],
+    keep_backups: usize,
+    log_file: &SharedLogFile,
+) -> Result<usize, String> {
+    let dir = update_dir(folder);
+    let staged: Vec<String> = files
+        .iter()
+        .filter(|item| resolve_dest(&dir, &item.dest).is_ok_and(|path| path.is_file()))
+        .map(|item| item.dest.clone())
+        .collect();
+
+    swap_with_backup(
+        folder,
+        &dir,
+        &staged,
+        removed,
+        Some(previous),
+        Some(keep_backups),
+        log_file,
+    )?;
+
+    let _ = fs::remove_dir_all(&dir);
+    Ok(staged.len())
+}
+
+async fn check_release(client: &Client, manifest: &StagedManifest) -> bool {
+    let (Some(source), Some(staged_version)) = (
+        manifest.config.source.as_ref(),
+        manifest.config.version.as_deref(),
+    ) else {
+        return true;
+    };
+
+    let live_version = fetch_json(client, &source.url)
+        .await
+        .ok()
+        .and_then(|data| config_version(&data, "default"));
+
+    if live_version.as_deref() == Some(staged_version) {
+        return true;
+    }
+
+    println!(
+        "{} Version {} is not live yet (current release: {})",
+        Status::warning(),
+        staged_version.yellow(),
+        live_version.as_deref().unwrap_or("unknown").cyan()
+    );
+    confirm("Apply the staged files anyway?")
+}
+
+pub async fn apply_staged(
+    client: &Client,
+    folder: &Path,
+    keep_backups: Option<usize>,
+    log_file: &SharedLogFile,
+) -> Result<usize, String> {
+    let manifest = load_staged_manifest(folder)?
+        .ok_or_else(|| format!("No completed predownload staged in {}", folder.display()))?;
+
+    if !check_release(client, &manifest).await {
+        return Err("Apply cancelled".to_string());
+    }
+
+    println!(
+        "{} Verifying {} staged files...",
+        Status::info(),
+        manifest.staged.len().to_string().cyan()
+    );
+    verify_staged(folder, &manifest).await?;
+
+    let filter = ResourceFilter::new(&manifest.filters)?;
+    let previous = load_snapshot(folder)?;
+    let new_files: HashSet<&str> = manifest.files.iter().map(|f| f.dest.as_str()).collect();
+    let removed: Vec<String> = previous
+        .iter()
+        .flat_map(|previous| &previous.files)
+        .map(|file| file.dest.clone())
+        .filter(|dest| !new_files.contains(dest.as_str()) && filter.matches(dest))
+        .collect();
+
+    println!("{} Applying staged files...", Status::info());
+    swap_with_backup(
+        folder,
+        &staging_dir(folder),
+        &manifest.staged,
+        &removed,
+        previous.as_ref(),
+        keep_backups,
+        log_file,
+    )?;
+
+    let snapshot = build_snapshot(
+        &manifest.config,
+        &manifest.files,
+        folder,
+        &Default::default(),
+    )
+    .and_then(|mut snapshot| {
+        record_patterns(&manifest.filters, &mut snapshot);
+        if let Some(previous) = &previous {
+            filter.carry_over(previous, &mut snapshot);
+        }
+        write_snapshot(folder, &snapshot)
+    })
+    .and_then(|_| write_launcher_config(folder, &manifest.config));
+    if let Err(e) = snapshot {
+        log_error(log_file, &e);
+        println!("{} {}", Status::warning(), e);
+    }
+
+    let _ = fs::remove_dir_all(staging_dir(folder));
+    let _ = fs::remove_file(manifest_path(folder));
+
+    Ok(manifest.staged.len())
+}
@@ -0,0 +1,229 @@
+use colored::Colorize;
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::{cfg::ResourceItem, status::Status},
+    download::filter::PatternSet,
+    io::{
+        detect::detect_install,
+        file::{STATE_DIR, resolve_dest, state_dir},
+        launcher::LAUNCHER_CONFIG_FILE,
+        logging::{SharedLogFile, log_error, unix_timestamp},
+        util::bytes_to_human,
+    },
+};
+
+const QUARANTINE_DIR: &str = "quarantine";
+// Without a snapshot or launcher config, at least this share of the index must already be there.
+const MIN_PRESENT_PERCENT: usize = 50;
+
+// Player data the index never lists but that must survive a cleanup.
+const PROTECTED_PATHS: [&str; 5] = [
+    LAUNCHER_CONFIG_FILE,
+    "Client/Saved/SaveGames/**",
+    "Client/Saved/Config/**",
+    "Client/Saved/LocalStorage/**",
+    "*.sav",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CleanAction {
+    Delete,
+    Quarantine,
+}
+
+pub struct StrayFile {
+    pub dest: String,
+    pub size: u64,
+}
+
+fn walk(
+    dir: &Path,
+    prefix: &str,
+    expected: &HashSet<&str>,
+    keep: &PatternSet,
+    stray: &mut Vec<StrayFile>,
+) -> Result<(), String> {
+    let entries =
+        fs::read_dir(dir).map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if prefix.is_empty() && name == STATE_DIR {
+            continue;
+        }
+
+        let dest = if prefix.is_empty() {
+            name
+        } else {
+            format!("{}/{}", prefix, name)
+        };
+
+        // Symlinks are never followed or removed; they may point outside the install.
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            walk(&entry.path(), &dest, expected, keep, stray)?;
+        } else if metadata.is_file() && !expected.contains(dest.as_str()) && !keep.matches(&dest) {
+            stray.push(StrayFile {
+                dest,
+                size: metadata.len(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+// Everything not in the index is cleaned up, so a wrong folder (e.g. a home directory) must be refused.
+pub fn check_install_folder(folder: &Path, resources: &[ResourceItem]) -> Result<(), String> {
+    if detect_install(folder).is_some() {
+        return Ok(());
+    }
+
+    let present = resources
+        .iter()
+        .filter(|item| resolve_dest(folder, &item.dest).is_ok_and(|path| path.is_file()))
+        .count();
+    if !resources.is_empty() && present * 100 >= resources.len() * MIN_PRESENT_PERCENT {
+        return Ok(());
+    }
+
+    Err(format!(
+        "{} does not look like a game install: no install snapshot or launcher config, and only {} of {} index files are present",
+        folder.display(),
+        present,
+        resources.len()
+    ))
+}
+
+pub fn find_stray_files(
+    folder: &Path,
+    resources: &[ResourceItem],
+    keep: &[String],
+) -> Result<Vec<StrayFile>, String> {
+    let keep: Vec<&str> = PROTECTED_PATHS
+        .into_iter()
+        .chain(keep.iter().map(String::as_str))
+        .collect();
+    let keep = PatternSet::new(&keep)?;
+    let expected: HashSet<&str> = resources.iter().map(|item| item.dest.as_str()).collect();
+
+    let mut stray = Vec::new();
+    walk(folder, "", &expected, &keep, &mut stray)?;
+    stray.sort_by(|a, b| a.dest.cmp(&b.dest));
+    Ok(stray)
+}
+
+pub fn print_stray_files(stray: &[StrayFile]) {
+    for file in stray {
+        println!(
+            "{} Not in the index: {} ({})",
+            Status::warning(),
+            file.dest,
+            bytes_to_human(file.size)
+        );
+    }
+
+    let total: u64 = stray.iter().map(|file| file.size).sum();
+    println!(
+        "{} Found {} stray files ({})",
+        Status::info(),
+        stray.len().to_string().yellow(),
+        bytes_to_human(total).yellow()
+    );
+}
+
+fn quarantine_dir(folder: &Path) -> PathBuf {
+    state_dir(folder)
+        .join(QUARANTINE_DIR)
+        .join(unix_timestamp().to_string())
+}
+
+fn clean_file(folder: &Path, dest: &str, quarantine: Option<&Path>) -> Result<(), String> {
+    let live = resolve_dest(folder, dest)?;
+
+    match quarantine {
+        Some(quarantine) => {
+            let moved = resolve_dest(quarantine, dest)?;
+            if let Some(parent) = moved.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+            }
+            fs::rename(&live, &moved).map_err(|e| format!("Failed to move {}: {}", dest, e))
+        }
+        None => fs::remove_file(&live).map_err(|e| format!("Failed to remove {}: {}", dest, e)),
+    }
+}
+
+pub fn clean_stray_files(
+    folder: &Path,
+    stray: &[StrayFile],
+    action: CleanAction,
+    log_file: &SharedLogFile,
+) -> (usize, Option<PathBuf>) {
+    let quarantine = (action == CleanAction::Quarantine).then(|| quarantine_dir(folder));
+    let mut cleaned = 0;
+
+    for file in stray {
+        match clean_file(folder, &file.dest, quarantine.as_deref()) {
+            Ok(()) => cleaned += 1,
+            Err(e) => log_error(log_file, &e),
+        }
+    }
+
+    (cleaned, quarantine)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(dest: &str) -> ResourceItem {
+        ResourceItem {
+            dest: dest.to_string(),
+            md5: None,
+            size: None,
+        }
+    }
+
+    #[test]
+    fn folders_without_the_game_are_refused() {
+        let folder = tempfile::tempdir().unwrap();
+        fs::write(folder.path().join("notes.txt"), "mine").unwrap();
+        let resources = [item("Client/a.pak"), item("Client/b.pak")];
+
+        let error = check_install_folder(folder.path(), &resources).unwrap_err();
+        assert!(error.contains("0 of 2"), "{}", error);
+        assert!(check_install_folder(folder.path(), &[]).is_err());
+    }
+
+    #[test]
+    fn folders_with_most_of_the_index_are_accepted() {
+        let folder = tempfile::tempdir().unwrap();
+        fs::create_dir(folder.path().join("Client")).unwrap();
+        fs::write(folder.path().join("Client/a.pak"), "a").unwrap();
+        let resources = [item("Client/a.pak"), item("Client/b.pak")];
+
+        assert!(check_install_folder(folder.path(), &resources).is_ok());
+        assert!(check_install_folder(folder.path(), &resources[1..]).is_err());
+    }
+
+    #[test]
+    fn protected_and_indexed_files_are_not_stray() {
+        let folder = tempfile::tempdir().unwrap();
+        fs::write(folder.path().join("a.pak"), "a").unwrap();
+        fs::write(folder.path().join("old.pak"), "old").unwrap();
+        fs::write(folder.path().join(LAUNCHER_CONFIG_FILE), "{}").unwrap();
+
+        let stray = find_stray_files(folder.path(), &[item("a.pak")], &[]).unwrap();
+        let dests: Vec<&str> = stray.iter().map(|file| file.dest.as_str()).collect();
+        assert_eq!(dests, ["old.pak"]);
+    }
+}
@@ -0,0 +1,191 @@
+use colored::Colorize;
+use std::{collections::HashMap, fs, path::Path};
+
+use crate::{
+    config::{cfg::ResourceItem, status::Status},
+    io::{
+        file::resolve_dest,
+        logging::{SharedLogFile, log_error},
+        snapshot::{InstallSnapshot, InstalledFile},
+        util::bytes_to_human,
+    },
+};
+
+pub struct UpdatePlan {
+    pub added: Vec<ResourceItem>,
+    pub changed: Vec<ResourceItem>,
+    pub unchanged: Vec<ResourceItem>,
+    pub removed: Vec<InstalledFile>,
+}
+
+impl UpdatePlan {
+    pub fn to_download(&self) -> Vec<ResourceItem> {
+        self.added.iter().chain(&self.changed).cloned().collect()
+    }
+}
+
+fn is_changed(old: &InstalledFile, new: &ResourceItem) -> bool {
+    let md5_known = old.md5.is_some() && new.md5.is_some();
+    let size_known = new.size.is_some();
+
+    if !md5_known && !size_known {
+        // Nothing to compare against, so treat the file as changed and let it be verified.
+        return true;
+    }
+
+    (md5_known && old.md5 != new.md5) || new.size.is_some_and(|size| size != old.size)
+}
+
+pub fn plan_update(previous: &InstallSnapshot, resources: &[ResourceItem]) -> UpdatePlan {
+    let mut old_files: HashMap<&str, &InstalledFile> = previous
+        .files
+        .iter()
+        .map(|file| (file.dest.as_str(), file))
+        .collect();
+
+    let mut plan = UpdatePlan {
+        added: Vec::new(),
+        changed: Vec::new(),
+        unchanged: Vec::new(),
+        removed: Vec::new(),
+    };
+
+    for item in resources {
+        match old_files.remove(item.dest.as_str()) {
+            None => plan.added.push(item.clone()),
+            Some(old) if is_changed(old, item) => plan.changed.push(item.clone()),
+            Some(_) => plan.unchanged.push(item.clone()),
+        }
+    }
+
+    plan.removed = previous
+        .files
+        .iter()
+        .filter(|file| old_files.contains_key(file.dest.as_str()))
+        .cloned()
+        .collect();
+
+    plan
+}
+
+pub fn print_update_plan(plan: &UpdatePlan, installed: Option<&str>, available: Option<&str>) {
+    println!(
+        "{} Updating {} -> {}",
+        Status::info(),
+        installed.unwrap_or("unknown").yellow(),
+        available.unwrap_or("unknown").cyan()
+    );
+    println!(
+        "{} Added: {}, changed: {}, unchanged: {}, removed: {}",
+        Status::info(),
+        plan.added.len().to_string().green(),
+        plan.changed.len().to_string().yellow(),
+        plan.unchanged.len().to_string().cyan(),
+        plan.removed.len().to_string().red()
+    );
+
+    for file in &plan.removed {
+        println!(
+            "{} No longer part of this version: {} ({})",
+            Status::warning(),
+            file.dest,
+            bytes_to_human(file.size)
+        );
+    }
+}
+
+pub fn remove_stale_files(
+    folder: &Path,
+    removed: &[InstalledFile],
+    log_file: &SharedLogFile,
+) -> usize {
+    let mut deleted = 0;
+
+    for file in removed {
+        let result =
+            resolve_dest(folder, &file.dest).and_then(|path| match fs::remove_file(&path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    Err(format!("{}: {}", file.dest, e))
+                }
+                _ => Ok(()),
+            });
+
+        match result {
+            Ok(()) => deleted += 1,
+            Err(e) => log_error(log_file, &format!("Failed to remove stale file {}", e)),
+        }
+    }
+
+    deleted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(dest: &str, md5: Option<&str>, size: Option<u64>) -> ResourceItem {
+        ResourceItem {
+            dest: dest.to_string(),
+            md5: md5.map(str::to_string),
+            size,
+        }
+    }
+
+    fn installed(dest: &str, md5: Option<&str>, size: u64) -> InstalledFile {
+        InstalledFile {
+            dest: dest.to_string(),
+            size,
+            md5: md5.map(str::to_string),
+            completed: 0,
+        }
+    }
+
+    fn snapshot(files: Vec<InstalledFile>) -> InstallSnapshot {
+        InstallSnapshot {
+            version: Some("1.0".to_string()),
+            files,
+            ..Default::default()
+        }
+    }
+
+    fn dests(items: &[ResourceItem]) -> Vec<&str> {
+        items.iter().map(|item| item.dest.as_str()).collect()
+    }
+
+    #[test]
+    fn classifies_files_against_the_snapshot() {
+        let previous = snapshot(vec![
+            installed("same", Some("aa"), 1),
+            installed("new-md5", Some("aa"), 1),
+            installed("new-size", None, 1),
+            installed("gone", Some("cc"), 3),
+        ]);
+        let resources = vec![
+            item("same", Some("aa"), Some(1)),
+            item("new-md5", Some("bb"), Some(1)),
+            item("new-size", Some("aa"), Some(2)),
+            item("added", Some("dd"), Some(4)),
+        ];
+
+        let plan = plan_update(&previous, &resources);
+        assert_eq!(dests(&plan.unchanged), ["same"]);
+        assert_eq!(dests(&plan.changed), ["new-md5", "new-size"]);
+        assert_eq!(dests(&plan.added), ["added"]);
+        assert_eq!(
+            plan.removed
+                .iter()
+                .map(|f| f.dest.as_str())
+                .collect::<Vec<_>>(),
+            ["gone"]
+        );
+        assert_eq!(dests(&plan.to_download()), ["added", "new-md5", "new-size"]);
+    }
+
+    #[test]
+    fn files_without_checksum_or_size_are_rechecked() {
+        let previous = snapshot(vec![installed("a", None, 1)]);
+        let plan = plan_update(&previous, &[item("a", None, None)]);
+
+        assert_eq!(dests(&plan.changed), ["a"]);
+    }
+}
@@ -0,0 +1,161 @@
+use colored::Colorize;
+use std::io::{self, Write};
+
+use crate::config::{cfg::ResourceItem, status::Status};
+
+// Stored instead of a language list so "all" is remembered as a choice, not mistaken for "never asked".
+pub const ALL_LANGUAGES: &str = "all";
+
+const VOICE_LANGUAGES: [(&str, &str); 4] = [
+    ("zh", "Chinese"),
+    ("en", "English"),
+    ("ja", "Japanese"),
+    ("ko", "Korean"),
+];
+
+// Voice-over lives in per-language folders below the Wwise audio tree,
+// e.g. `Client/Content/Aki/WwiseAudio_Generated/Media/ja/...`.
+pub fn voice_language(dest: &str) -> Option<&'static str> {
+    let mut in_audio = false;
+
+    for component in dest.split('/') {
+        let lower = component.to_ascii_lowercase();
+        if in_audio && let Some((code, _)) = VOICE_LANGUAGES.iter().find(|(code, _)| *code == lower)
+        {
+            return Some(code);
+        }
+        in_audio |= lower.contains("wwise") || lower.contains("audio");
+    }
+
+    None
+}
+
+pub fn wants_all(languages: &[String]) -> bool {
+    languages.iter().any(|code| code == ALL_LANGUAGES)
+}
+
+pub fn parse_voice_languages(value: &str) -> Result<Vec<String>, String> {
+    let mut languages = Vec::new();
+
+    for code in value
+        .split([',', ' '])
+        .map(|code| code.trim().to_ascii_lowercase())
+        .filter(|code| !code.is_empty())
+    {
+        if code == ALL_LANGUAGES {
+            return Ok(vec![code]);
+        }
+        if !VOICE_LANGUAGES.iter().any(|(known, _)| *known == code) {
+            return Err(format!(
+                "Unknown voice language {:?}, expected one of: {}",
+                code,
+                language_codes(VOICE_LANGUAGES.iter().map(|(code, _)| *code))
+            ));
+        }
+        if !languages.contains(&code) {
+            languages.push(code);
+        }
+    }
+
+    Ok(languages)
+}
+
+fn language_codes<'a>(codes: impl Iterator<Item = &'a str>) -> String {
+    codes.collect::<Vec<_>>().join(", ")
+}
+
+fn available_languages(resources: &[ResourceItem]) -> Vec<&'static str> {
+    VOICE_LANGUAGES
+        .iter()
+        .map(|(code, _)| *code)
+        .filter(|code| {
+            resources
+                .iter()
+                .any(|item| voice_language(&item.dest) == Some(code))
+        })
+        .collect()
+}
+
+fn ask_voice_languages(available: &[&str]) -> Vec<String> {
+    for (code, name) in VOICE_LANGUAGES
+        .iter()
+        .filter(|(code, _)| available.contains(code))
+    {
+        println!("{} {} - {}", Status::info(), code.cyan(), name);
+    }
+    print!(
+        "{} Voice languages to install, comma separated [default all]: ",
+        Status::question()
+    );
+    io::stdout().flush().unwrap();
+
+    let mut input = String::new();
+    if io::stdin().read_line(&mut input).is_err() {
+        return vec![ALL_LANGUAGES.to_string()];
+    }
+
+    match parse_voice_languages(&input) {
+        Ok(languages) if languages.is_empty() => vec![ALL_LANGUAGES.to_string()],
+        Ok(languages) => languages,
+        Err(e) => {
+            println!("{} {}, installing all languages", Status::warning(), e);
+            vec![ALL_LANGUAGES.to_string()]
+        }
+    }
+}
+
+pub fn choose_voice_languages(
+    resources: &[ResourceItem],
+    remembered: Option<Vec<String>>,
+) -> Option<Vec<String>> {
+    let available = available_languages(resources);
+    if available.len() < 2 {
+        return None;
+    }
+
+    let languages = match remembered {
+        Some(languages) => {
+            println!(
+                "{} Keeping voice languages from the install snapshot",
+                Status::info()
+            );
+            languages
+        }
+        None => ask_voice_languages(&available),
+    };
+
+    println!(
+        "{} Voice languages: {}",
+        Status::info(),
+        language_codes(languages.iter().map(String::as_str)).cyan()
+    );
+    Some(languages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_language_lists() {
+        assert_eq!(parse_voice_languages("JA, en ja").unwrap(), ["ja", "en"]);
+        assert!(parse_voice_languages("").unwrap().is_empty());
+        assert!(parse_voice_languages("ja,fr").is_err());
+    }
+
+    #[test]
+    fn all_is_an_explicit_choice() {
+        let languages = parse_voice_languages("ja,all").unwrap();
+        assert_eq!(languages, [ALL_LANGUAGES]);
+        assert!(wants_all(&languages));
+    }
+
+    #[test]
+    fn voice_folders_sit_below_the_audio_tree() {
+        assert_eq!(
+            voice_language("Client/Content/Aki/WwiseAudio_Generated/Media/ja/a.wem"),
+            Some("ja")
+        );
+        assert_eq!(voice_language("Client/ja/readme.txt"), None);
+    }
+}
@@ -0,0 +1,217 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use crate::io::file::{calculate_md5, clone_or_copy};
+
+// Bytes held by the cache, measured on the first store of a run and kept current after that.
+static USAGE: Mutex<Option<u64>> = Mutex::new(None);
+
+fn entry_path(cache: &Path, md5: &str) -> Option<PathBuf> {
+    let md5 = md5.to_ascii_lowercase();
+    if md5.len() != 32 || !md5.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    Some(cache.join(&md5[..2]).join(md5))
+}
+
+// Entry mtimes double as last-use times for eviction.
+fn touch(path: &Path) {
+    if let Ok(file) = fs::File::options().write(true).open(path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+}
+
+pub async fn restore_from_cache(
+    cache: &Path,
+    md5: &str,
+    size: Option<u64>,
+    dest: &Path,
+) -> Result<bool, String> {
+    let Some(entry) = entry_path(cache, md5) else {
+        return Ok(false);
+    };
+    let Ok(metadata) = fs::metadata(&entry) else {
+        return Ok(false);
+    };
+    if size.is_some_and(|size| metadata.len() != size) {
+        let _ = fs::remove_file(&entry);
+        return Ok(false);
+    }
+
+    let (source, target) = (entry.clone(), dest.to_path_buf());
+    tokio::task::spawn_blocking(move || clone_or_copy(&source, &target))
+        .await
+        .map_err(|e| format!("Failed to join cache task: {}", e))??;
+    if !calculate_md5(dest).await?.eq_ignore_ascii_case(md5) {
+        // A damaged entry must not be handed out again.
+        let _ = fs::remove_file(dest);
+        let _ = fs::remove_file(&entry);
+        return Ok(false);
+    }
+
+    touch(&entry);
+    Ok(true)
+}
+
+pub async fn store_in_cache(
+    cache: &Path,
+    md5: &str,
+    source: &Path,
+    limit: Option<u64>,
+) -> Result<(), String> {
+    let (cache, md5, source) = (cache.to_path_buf(), md5.to_string(), source.to_path_buf());
+    tokio::task::spawn_blocking(move || store_in_cache_sync(&cache, &md5, &source, limit))
+        .await
+        .map_err(|e| format!("Failed to join cache task: {}", e))?
+}
+
+fn store_in_cache_sync(
+    cache: &Path,
+    md5: &str,
+    source: &Path,
+    limit: Option<u64>,
+) -> Result<(), String> {
+    let Some(entry) = entry_path(cache, md5) else {
+        return Ok(());
+    };
+    if entry.is_file() {
+        touch(&entry);
+        return Ok(());
+    }
+
+    clone_or_copy(source, &entry)?;
+    let Some(limit) = limit else {
+        return Ok(());
+    };
+
+    let mut usage = USAGE.lock().unwrap_or_else(|e| e.into_inner());
+    let used = match *usage {
+        Some(used) => used + fs::metadata(&entry).map(|m| m.len()).unwrap_or(0),
+        None => cache_usage(cache),
+    };
+
+    // Evicting a tenth below the limit keeps every later store from rescanning the cache.
+    *usage = Some(if used > limit {
+        let (_, freed) = evict(cache, limit - limit / 10)?;
+        used.saturating_sub(freed)
+    } else {
+        used
+    });
+
+    Ok(())
+}
+
+fn cache_entries(cache: &Path) -> Result<Vec<(PathBuf, u64, SystemTime)>, String> {
+    let buckets =
+        fs::read_dir(cache).map_err(|e| format!("Failed to read {}: {}", cache.display(), e))?;
+
+    Ok(buckets
+        .filter_map(Result::ok)
+        .filter(|bucket| bucket.path().is_dir())
+        .filter_map(|bucket| fs::read_dir(bucket.path()).ok())
+        .flatten()
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let used = metadata.modified().ok()?;
+            metadata
+                .is_file()
+                .then(|| (entry.path(), metadata.len(), used))
+        })
+        .collect())
+}
+
+fn cache_usage(cache: &Path) -> u64 {
+    cache_entries(cache)
+        .map(|entries| entries.iter().map(|(_, size, _)| size).sum())
+        .unwrap_or(0)
+}
+
+pub fn evict_cache(cache: &Path, limit: u64) -> Result<(usize, u64), String> {
+    let mut usage = USAGE.lock().unwrap_or_else(|e| e.into_inner());
+    *usage = None;
+    evict(cache, limit)
+}
+
+fn evict(cache: &Path, limit: u64) -> Result<(usize, u64), String> {
+    let mut entries = cache_entries(cache)?;
+    let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+    entries.sort_by_key(|(_, _, used)| *used);
+
+    let mut evicted = 0;
+    let mut freed = 0;
+    for (path, size, _) in entries {
+        if total <= limit {
+            break;
+        }
+        fs::remove_file(&path).map_err(|e| format!("Failed to evict {}: {}", path.display(), e))?;
+        total -= size;
+        freed += size;
+        evicted += 1;
+    }
+
+    Ok((evicted, freed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MD5: &str = "9e107d9d372bb6826bd81d3542a419d6";
+
+    #[tokio::test]
+    async fn cached_files_do_not_share_the_install_inode() {
+        let cache = tempfile::tempdir().unwrap();
+        let folder = tempfile::tempdir().unwrap();
+        let installed = folder.path().join("file.txt");
+        fs::write(&installed, "The quick brown fox jumps over the lazy dog").unwrap();
+
+        store_in_cache(cache.path(), MD5, &installed, None)
+            .await
+            .unwrap();
+        fs::write(&installed, "patched in place").unwrap();
+
+        let entry = entry_path(cache.path(), MD5).unwrap();
+        assert_eq!(
+            fs::read_to_string(&entry).unwrap(),
+            "The quick brown fox jumps over the lazy dog"
+        );
+
+        let restored = folder.path().join("restored.txt");
+        assert!(
+            restore_from_cache(cache.path(), MD5, None, &restored)
+                .await
+                .unwrap()
+        );
+        fs::write(&restored, "patched again").unwrap();
+        assert_eq!(fs::read(&entry).unwrap().len(), 43);
+    }
+
+    #[test]
+    fn eviction_drops_the_least_recently_used_entries() {
+        let cache = tempfile::tempdir().unwrap();
+        let old = cache.path().join("aa").join("a".repeat(32));
+        let new = cache.path().join("bb").join("b".repeat(32));
+        for path in [&old, &new] {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, [0; 100]).unwrap();
+        }
+        let file = fs::File::options().write(true).open(&old).unwrap();
+        file.set_modified(SystemTime::UNIX_EPOCH).unwrap();
+
+        assert_eq!(evict_cache(cache.path(), 150).unwrap(), (1, 100));
+        assert!(!old.exists());
+        assert!(new.exists());
+    }
+
+    #[test]
+    fn invalid_hashes_are_not_cached() {
+        assert!(entry_path(Path::new("cache"), "not-a-hash").is_none());
+        assert!(entry_path(Path::new("cache"), &"A".repeat(32)).is_some());
+    }
+}
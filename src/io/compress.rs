@@ -0,0 +1,92 @@
+use flate2::read::{MultiGzDecoder, ZlibDecoder};
+use std::{fs, io::Read, path::Path};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+// A zlib header is CM=8 in the low nibble plus a check value making the pair divisible by 31.
+fn is_zlib(data: &[u8]) -> bool {
+    data.len() >= 2
+        && data[0] & 0x0f == 8
+        && u16::from_be_bytes([data[0], data[1]]).is_multiple_of(31)
+}
+
+// Mirrors may serve `.gz` files without a Content-Encoding header, so the payload itself is checked.
+pub fn decompress(data: Vec<u8>) -> Result<Vec<u8>, String> {
+    let mut output = Vec::new();
+    if data.starts_with(&GZIP_MAGIC) {
+        MultiGzDecoder::new(&data[..])
+            .read_to_end(&mut output)
+            .map_err(|e| format!("Invalid gzip data: {}", e))?;
+    } else if is_zlib(&data) {
+        ZlibDecoder::new(&data[..])
+            .read_to_end(&mut output)
+            .map_err(|e| format!("Invalid zlib data: {}", e))?;
+    } else {
+        return Ok(data);
+    }
+    Ok(output)
+}
+
+pub fn decode_text(data: Vec<u8>) -> Result<String, String> {
+    String::from_utf8(decompress(data)?).map_err(|e| format!("Invalid UTF-8: {}", e))
+}
+
+pub fn read_text_file(path: &Path) -> Result<String, String> {
+    let data = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    decode_text(data).map_err(|e| format!("{} in {}", e, path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{
+        Compression,
+        write::{GzEncoder, ZlibEncoder},
+    };
+    use std::io::Write;
+
+    const JSON: &str = r#"{"resource": []}"#;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn zlib(data: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn zlib_headers_are_recognised() {
+        assert!(is_zlib(&zlib(JSON.as_bytes())));
+        assert!(is_zlib(&[0x78, 0x9c]));
+        assert!(is_zlib(&[0x78, 0x01]));
+        assert!(!is_zlib(JSON.as_bytes()));
+        assert!(!is_zlib(&GZIP_MAGIC));
+        assert!(!is_zlib(&[0x78]));
+        assert!(!is_zlib(&[]));
+    }
+
+    #[test]
+    fn decompress_handles_every_encoding() {
+        assert_eq!(decode_text(JSON.as_bytes().to_vec()).unwrap(), JSON);
+        assert_eq!(decode_text(gzip(JSON.as_bytes())).unwrap(), JSON);
+        assert_eq!(decode_text(zlib(JSON.as_bytes())).unwrap(), JSON);
+
+        let mut members = gzip(b"{\"resource\": ");
+        members.extend(gzip(b"[]}"));
+        assert_eq!(decode_text(members).unwrap(), JSON);
+    }
+
+    #[test]
+    fn broken_payloads_are_errors() {
+        let mut truncated = gzip(JSON.as_bytes());
+        truncated.truncate(truncated.len() / 2);
+        assert!(decompress(truncated).is_err());
+        assert!(decompress(vec![0x78, 0x9c, 0xff, 0xff]).is_err());
+        assert!(decode_text(vec![0xc3, 0x28]).is_err());
+    }
+}
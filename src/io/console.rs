@@ -3,6 +3,11 @@ use colored::Colorize;
 use std::{io, path::Path};
 
 pub fn print_results(success: usize, total: usize, folder: &Path) {
+    print_summary(success, total, folder);
+    pause_before_exit();
+}
+
+pub fn print_summary(success: usize, total: usize, folder: &Path) {
     let title = if success == total {
         " DOWNLOAD COMPLETE ".on_blue().white().bold()
     } else {
@@ -25,6 +30,9 @@ pub fn print_results(success: usize, total: usize, folder: &Path) {
         Status::info(),
         folder.display().to_string().cyan()
     );
+}
+
+pub fn pause_before_exit() {
     println!("\n{} Press Enter to exit...", Status::warning());
     let _ = io::stdin().read_line(&mut String::new());
 }
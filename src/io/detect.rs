@@ -0,0 +1,283 @@
+use colored::Colorize;
+use reqwest::Client;
+use std::{fs, path::Path};
+
+use crate::{
+    config::{
+        cfg::{Config, ResourceItem},
+        status::Status,
+    },
+    io::{
+        file::{STATE_DIR, calculate_md5, resolve_dest},
+        launcher::installed_version,
+        logging::{SharedLogFile, unix_timestamp},
+        snapshot::{InstallSnapshot, InstalledFile, load_snapshot},
+        util::parse_resources,
+    },
+    network::client::{candidate_configs, fetch_index},
+};
+
+const HASH_SAMPLE_SIZE: usize = 8;
+const HASH_SAMPLE_MAX_BYTES: u64 = 64 * 1024 * 1024;
+
+pub enum InstallSource {
+    Snapshot(InstallSnapshot),
+    // Built from the files on disk after a hash sample matched one of the candidate indexes.
+    Sampled(InstallSnapshot),
+    Launcher,
+    Unknown,
+}
+
+pub struct DetectedInstall {
+    pub version: Option<String>,
+    pub source: InstallSource,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum InstallAction {
+    Fresh,
+    Update,
+    Repair,
+}
+
+pub fn detect_install(folder: &Path) -> Option<DetectedInstall> {
+    if let Ok(Some(snapshot)) = load_snapshot(folder) {
+        return Some(DetectedInstall {
+            version: snapshot.version.clone(),
+            source: InstallSource::Snapshot(snapshot),
+        });
+    }
+
+    installed_version(folder).map(|version| DetectedInstall {
+        version: Some(version),
+        source: InstallSource::Launcher,
+    })
+}
+
+pub fn report_install(
+    detected: Option<&DetectedInstall>,
+    available: Option<&str>,
+) -> InstallAction {
+    let available = available.unwrap_or("unknown");
+
+    let Some(detected) = detected else {
+        println!(
+            "{} No existing install found, available: {}",
+            Status::info(),
+            available.cyan()
+        );
+        return InstallAction::Fresh;
+    };
+
+    let installed = detected.version.as_deref().unwrap_or("unknown");
+    let source = match detected.source {
+        InstallSource::Snapshot(_) => "install snapshot",
+        InstallSource::Sampled(_) => "file sample",
+        InstallSource::Launcher => "launcher config",
+        InstallSource::Unknown => "files on disk",
+    };
+    println!(
+        "{} Installed: {} (from {}), available: {}",
+        Status::info(),
+        installed.yellow(),
+        source,
+        available.cyan()
+    );
+
+    match &detected.source {
+        InstallSource::Snapshot(_) | InstallSource::Sampled(_) if installed != available => {
+            InstallAction::Update
+        }
+        _ => InstallAction::Repair,
+    }
+}
+
+pub async fn sample_install(folder: &Path, resources: &[ResourceItem]) -> Option<(usize, usize)> {
+    let candidates: Vec<&ResourceItem> = resources
+        .iter()
+        .filter(|item| item.md5.is_some())
+        .filter(|item| item.size.is_none_or(|size| size <= HASH_SAMPLE_MAX_BYTES))
+        .collect();
+
+    // Spread the sample over the whole index instead of hashing one directory.
+    let step = (candidates.len() / HASH_SAMPLE_SIZE).max(1);
+    let mut present = 0;
+    let mut matching = 0;
+
+    for item in candidates.iter().step_by(step).take(HASH_SAMPLE_SIZE) {
+        let Ok(path) = resolve_dest(folder, &item.dest) else {
+            continue;
+        };
+        if !path.is_file() {
+            continue;
+        }
+
+        present += 1;
+        if calculate_md5(&path).await.ok().as_deref() == item.md5.as_deref() {
+            matching += 1;
+        }
+    }
+
+    (present > 0).then_some((matching, present))
+}
+
+fn has_files(folder: &Path) -> bool {
+    fs::read_dir(folder).is_ok_and(|mut entries| {
+        entries.any(|entry| entry.is_ok_and(|entry| entry.file_name() != STATE_DIR))
+    })
+}
+
+// Only files whose size fits the matched index count as installed; anything missing gets downloaded.
+fn sampled_snapshot(config: &Config, folder: &Path, resources: &[ResourceItem]) -> InstallSnapshot {
+    let files = resources
+        .iter()
+        .filter_map(|item| {
+            let size = fs::metadata(resolve_dest(folder, &item.dest).ok()?)
+                .ok()?
+                .len();
+            item.size
+                .is_none_or(|expected| expected == size)
+                .then(|| InstalledFile {
+                    dest: item.dest.clone(),
+                    size,
+                    md5: item.md5.clone(),
+                    completed: 0,
+                })
+        })
+        .collect();
+
+    InstallSnapshot {
+        version: config.version.clone(),
+        source: config.source.clone(),
+        index_url: config.index_url.clone(),
+        written: unix_timestamp(),
+        voice_languages: None,
+        include: Vec::new(),
+        exclude: Vec::new(),
+        files,
+    }
+}
+
+// Without a snapshot of our own, hash a sample of the folder against every candidate index.
+pub async fn identify_install(
+    client: &Client,
+    config: &Config,
+    folder: &Path,
+    detected: Option<DetectedInstall>,
+    log_file: &SharedLogFile,
+) -> Option<DetectedInstall> {
+    if matches!(
+        detected.as_ref().map(|detected| &detected.source),
+        Some(InstallSource::Snapshot(_))
+    ) || !has_files(folder)
+    {
+        return detected;
+    }
+
+    println!(
+        "{} Sampling existing files to identify the install...",
+        Status::info()
+    );
+    let mut found_files = false;
+
+    for candidate in candidate_configs(client, config).await {
+        let Ok(resources) = fetch_index(client, &candidate, log_file)
+            .await
+            .and_then(|data| parse_resources(&data))
+            .map(|index| index.resources)
+        else {
+            continue;
+        };
+
+        match sample_install(folder, &resources).await {
+            Some((matching, sampled)) if matching == sampled => {
+                println!(
+                    "{} All {} sampled files match version {}",
+                    Status::info(),
+                    sampled,
+                    candidate.version.as_deref().unwrap_or("unknown").cyan()
+                );
+                return Some(DetectedInstall {
+                    version: candidate.version.clone(),
+                    source: InstallSource::Sampled(sampled_snapshot(
+                        &candidate, folder, &resources,
+                    )),
+                });
+            }
+            Some((matching, sampled)) => {
+                println!(
+                    "{} {}/{} sampled files match version {}",
+                    Status::info(),
+                    matching,
+                    sampled,
+                    candidate.version.as_deref().unwrap_or("unknown")
+                );
+                found_files = true;
+            }
+            None => {}
+        }
+    }
+
+    match detected {
+        Some(detected) => Some(detected),
+        None if found_files => Some(DetectedInstall {
+            version: None,
+            source: InstallSource::Unknown,
+        }),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(dest: &str, size: Option<u64>) -> ResourceItem {
+        ResourceItem {
+            dest: dest.to_string(),
+            md5: Some("00".repeat(16)),
+            size,
+        }
+    }
+
+    #[test]
+    fn sampled_snapshot_only_keeps_files_on_disk_with_the_expected_size() {
+        let folder = tempfile::tempdir().unwrap();
+        fs::write(folder.path().join("a.pak"), b"1234").unwrap();
+        fs::write(folder.path().join("b.pak"), b"12").unwrap();
+        fs::write(folder.path().join("c.pak"), b"123").unwrap();
+
+        let config = Config {
+            index_url: "index.json".to_string(),
+            version: Some("2.0".to_string()),
+            ..Default::default()
+        };
+        let resources = [
+            item("a.pak", Some(4)),
+            item("b.pak", Some(4)),
+            item("c.pak", None),
+            item("missing.pak", Some(1)),
+        ];
+
+        let snapshot = sampled_snapshot(&config, folder.path(), &resources);
+        let files: Vec<(&str, u64)> = snapshot
+            .files
+            .iter()
+            .map(|file| (file.dest.as_str(), file.size))
+            .collect();
+        assert_eq!(files, [("a.pak", 4), ("c.pak", 3)]);
+        assert_eq!(snapshot.version.as_deref(), Some("2.0"));
+    }
+
+    #[test]
+    fn state_dir_alone_is_not_an_install() {
+        let folder = tempfile::tempdir().unwrap();
+        assert!(!has_files(folder.path()));
+
+        fs::create_dir(folder.path().join(STATE_DIR)).unwrap();
+        assert!(!has_files(folder.path()));
+
+        fs::write(folder.path().join("Client.exe"), b"").unwrap();
+        assert!(has_files(folder.path()));
+    }
+}
@@ -0,0 +1,202 @@
+use colored::Colorize;
+use indicatif::ProgressBar;
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use crate::{
+    config::status::Status, download::progress::DownloadProgress, io::util::bytes_to_human,
+};
+
+pub const LOW_SPACE_MARGIN: u64 = 512 * 1024 * 1024;
+const MONITOR_INTERVAL: Duration = Duration::from_secs(2);
+
+#[cfg(unix)]
+pub fn available_space(path: &Path) -> io::Result<u64> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    #[allow(clippy::unnecessary_cast)]
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(windows)]
+pub fn available_space(path: &Path) -> io::Result<u64> {
+    use std::os::windows::ffi::OsStrExt;
+
+    unsafe extern "system" {
+        fn GetDiskFreeSpaceExW(
+            directory: *const u16,
+            free_bytes_available: *mut u64,
+            total_bytes: *mut u64,
+            total_free_bytes: *mut u64,
+        ) -> i32;
+    }
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut available = 0_u64;
+
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(available)
+}
+
+pub fn check_free_space(folder: &Path, required: u64) -> Result<u64, String> {
+    let available = available_space(folder)
+        .map_err(|e| format!("Failed to query free space on {}: {}", folder.display(), e))?;
+
+    if available < required.saturating_add(LOW_SPACE_MARGIN) {
+        return Err(format!(
+            "Not enough free space in {}: {} required (plus {} safety margin), {} available",
+            folder.display(),
+            bytes_to_human(required),
+            bytes_to_human(LOW_SPACE_MARGIN),
+            bytes_to_human(available)
+        ));
+    }
+
+    Ok(available)
+}
+
+// Identifies the filesystem a folder lives on, so targets sharing one are checked together.
+#[cfg(unix)]
+fn filesystem_id(path: &Path) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+
+    let existing = path.ancestors().find(|ancestor| ancestor.exists())?;
+    std::fs::metadata(existing)
+        .ok()
+        .map(|metadata| metadata.dev().to_string())
+}
+
+#[cfg(not(unix))]
+fn filesystem_id(path: &Path) -> Option<String> {
+    match path.components().next()? {
+        std::path::Component::Prefix(prefix) => {
+            Some(prefix.as_os_str().to_string_lossy().to_ascii_uppercase())
+        }
+        _ => None,
+    }
+}
+
+// Each failure lists the indexes of the folders that share the short filesystem.
+pub fn check_shared_space(needs: &[(&Path, u64)]) -> Vec<(Vec<usize>, String)> {
+    let mut groups: Vec<(String, Vec<usize>)> = Vec::new();
+    for (index, (folder, _)) in needs.iter().enumerate() {
+        let Some(id) = filesystem_id(folder) else {
+            continue;
+        };
+        match groups.iter_mut().find(|(group, _)| *group == id) {
+            Some((_, members)) => members.push(index),
+            None => groups.push((id, vec![index])),
+        }
+    }
+
+    groups
+        .into_iter()
+        .filter(|(_, members)| members.len() > 1)
+        .filter_map(|(_, members)| {
+            let required = members.iter().map(|&index| needs[index].1).sum();
+            check_free_space(needs[members[0]].0, required)
+                .err()
+                .map(|e| {
+                    (
+                        members,
+                        format!("Targets on the same filesystem combined: {}", e),
+                    )
+                })
+        })
+        .collect()
+}
+
+// One monitor watches every folder of a pass and pauses while any of them is low,
+// so a folder with room cannot resume what another one paused.
+pub fn start_disk_monitor(
+    folders: Vec<PathBuf>,
+    should_stop: Arc<AtomicBool>,
+    progress: DownloadProgress,
+    total_pb: ProgressBar,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        while !should_stop.load(Ordering::SeqCst) {
+            let lowest = folders
+                .iter()
+                .filter_map(|folder| available_space(folder).ok().map(|space| (space, folder)))
+                .min_by_key(|(space, _)| *space);
+
+            if let Some((available, folder)) = lowest {
+                let paused = progress.paused.load(Ordering::SeqCst);
+
+                // Resume only once there is clearly room again so tasks do not flap.
+                if !paused && available < LOW_SPACE_MARGIN {
+                    progress.paused.store(true, Ordering::SeqCst);
+                    total_pb.println(format!(
+                        "{} Low disk space in {} ({} free), pausing downloads until space is freed",
+                        Status::warning(),
+                        folder.display(),
+                        bytes_to_human(available).red()
+                    ));
+                } else if paused && available >= LOW_SPACE_MARGIN * 2 {
+                    progress.paused.store(false, Ordering::SeqCst);
+                    total_pb.println(format!(
+                        "{} Disk space available again ({} free), resuming downloads",
+                        Status::success(),
+                        bytes_to_human(available).green()
+                    ));
+                }
+            }
+
+            tokio::time::sleep(MONITOR_INTERVAL).await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn targets_on_one_filesystem_are_checked_together() {
+        let root = tempfile::tempdir().unwrap();
+        let a = root.path().join("a");
+        let b = root.path().join("b");
+        std::fs::create_dir(&a).unwrap();
+        let available = available_space(root.path()).unwrap();
+        if available < LOW_SPACE_MARGIN * 4 {
+            return;
+        }
+
+        // Each half fits on its own, together they do not; `b` does not exist yet.
+        let half = available / 2 - LOW_SPACE_MARGIN / 4;
+        assert!(check_free_space(&a, half).is_ok());
+        let short = check_shared_space(&[(&a, half), (&b, half)]);
+        assert_eq!(short.len(), 1);
+        assert_eq!(short[0].0, [0, 1]);
+
+        assert!(check_shared_space(&[(&a, 1), (&b, 1)]).is_empty());
+        assert!(check_shared_space(&[(&a, u64::MAX / 2)]).is_empty());
+    }
+}
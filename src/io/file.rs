@@ -4,9 +4,13 @@ use std::{
     io::{self, BufReader, Write},
     path::{Path, PathBuf},
 };
+use tokio::io::AsyncWriteExt;
 
 use crate::config::status::Status;
 
+pub const STATE_DIR: &str = ".wuwa-downloader";
+pub const WRITE_BLOCK_SIZE: usize = 4 * 1024 * 1024;
+
 fn calculate_md5_sync(path: &Path) -> io::Result<String> {
     let file = fs::File::open(path)?;
     let mut reader = BufReader::with_capacity(262_144, file);
@@ -56,6 +60,207 @@ pub async fn file_size(path: &Path) -> u64 {
         .unwrap_or(0)
 }
 
+// Reserves disk blocks without changing the file length, so resume offsets stay valid.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn preallocate(file: &fs::File, size: u64) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let ret = unsafe {
+        libc::fallocate(
+            file.as_raw_fd(),
+            libc::FALLOC_FL_KEEP_SIZE,
+            0,
+            size as libc::off_t,
+        )
+    };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+#[cfg(windows)]
+pub fn preallocate(file: &fs::File, size: u64) -> io::Result<()> {
+    use std::{ffi::c_void, os::windows::io::AsRawHandle};
+
+    const FILE_ALLOCATION_INFO_CLASS: u32 = 5;
+
+    unsafe extern "system" {
+        fn SetFileInformationByHandle(
+            handle: *mut c_void,
+            class: u32,
+            info: *const c_void,
+            size: u32,
+        ) -> i32;
+    }
+
+    let allocation_size = size as i64;
+    let ok = unsafe {
+        SetFileInformationByHandle(
+            file.as_raw_handle(),
+            FILE_ALLOCATION_INFO_CLASS,
+            &allocation_size as *const i64 as *const c_void,
+            std::mem::size_of::<i64>() as u32,
+        )
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", windows)))]
+pub fn preallocate(_file: &fs::File, _size: u64) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn reflink(source: &Path, dest: &Path) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let src = fs::File::open(source)?;
+    let dst = fs::File::create(dest)?;
+    let ret = unsafe { libc::ioctl(dst.as_raw_fd(), libc::FICLONE, src.as_raw_fd()) };
+    if ret != 0 {
+        let e = io::Error::last_os_error();
+        drop(dst);
+        let _ = fs::remove_file(dest);
+        return Err(e);
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+pub fn reflink(source: &Path, dest: &Path) -> io::Result<()> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let src = CString::new(source.as_os_str().as_bytes())?;
+    let dst = CString::new(dest.as_os_str().as_bytes())?;
+    if unsafe { libc::clonefile(src.as_ptr(), dst.as_ptr(), 0) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos")))]
+pub fn reflink(_source: &Path, _dest: &Path) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+// Writing into a hardlinked file would change every install sharing it, so detach it first.
+#[cfg(unix)]
+pub fn unlink_if_shared(path: &Path) {
+    use std::os::unix::fs::MetadataExt;
+
+    if fs::metadata(path).is_ok_and(|metadata| metadata.nlink() > 1) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+#[cfg(not(unix))]
+pub fn unlink_if_shared(_path: &Path) {}
+
+#[derive(Clone, Copy)]
+pub enum LinkKind {
+    Reflink,
+    Hardlink,
+    Copy,
+}
+
+pub fn link_or_copy(source: &Path, dest: &Path) -> Result<LinkKind, String> {
+    place(source, dest, true)
+}
+
+// Cache entries outlive any install, so they must never share an inode with one.
+pub fn clone_or_copy(source: &Path, dest: &Path) -> Result<LinkKind, String> {
+    place(source, dest, false)
+}
+
+fn place(source: &Path, dest: &Path, hardlink: bool) -> Result<LinkKind, String> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+
+    let mut tmp_name = dest.as_os_str().to_owned();
+    tmp_name.push(".link");
+    let tmp = PathBuf::from(tmp_name);
+    let _ = fs::remove_file(&tmp);
+
+    // Hardlinks are only used where unlink_if_shared can detach them before a rewrite.
+    let kind = if reflink(source, &tmp).is_ok() {
+        LinkKind::Reflink
+    } else if hardlink && cfg!(unix) && fs::hard_link(source, &tmp).is_ok() {
+        LinkKind::Hardlink
+    } else {
+        fs::copy(source, &tmp)
+            .map_err(|e| format!("Failed to copy {}: {}", source.display(), e))?;
+        LinkKind::Copy
+    };
+
+    fs::rename(&tmp, dest).map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        format!("Failed to place {}: {}", dest.display(), e)
+    })?;
+
+    Ok(kind)
+}
+
+pub struct BlockWriter {
+    file: tokio::fs::File,
+    buffer: Vec<u8>,
+    offset: u64,
+}
+
+impl BlockWriter {
+    pub fn new(file: tokio::fs::File, offset: u64) -> Self {
+        Self {
+            file,
+            buffer: Vec::with_capacity(WRITE_BLOCK_SIZE * 2),
+            offset,
+        }
+    }
+
+    pub async fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.buffer.extend_from_slice(data);
+
+        // Only write whole blocks aligned to the file offset; the tail waits for more data.
+        let misalign = (self.offset % WRITE_BLOCK_SIZE as u64) as usize;
+        let aligned_end = (misalign + self.buffer.len()) / WRITE_BLOCK_SIZE * WRITE_BLOCK_SIZE;
+        if aligned_end > misalign {
+            let writable = aligned_end - misalign;
+            self.file.write_all(&self.buffer[..writable]).await?;
+            self.buffer.drain(..writable);
+            self.offset += writable as u64;
+        }
+
+        Ok(())
+    }
+
+    pub async fn flush(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            self.file.write_all(&self.buffer).await?;
+            self.offset += self.buffer.len() as u64;
+            self.buffer.clear();
+        }
+
+        self.file.flush().await
+    }
+
+    pub async fn finish(mut self, fsync: bool) -> io::Result<()> {
+        self.flush().await?;
+        if fsync {
+            self.file.sync_all().await?;
+        }
+
+        Ok(())
+    }
+}
+
 pub fn get_filename(path: &str) -> String {
     Path::new(path)
         .file_name()
@@ -64,6 +269,92 @@ pub fn get_filename(path: &str) -> String {
         .to_string()
 }
 
+fn check_symlinks(folder: &Path, path: &Path) -> Result<(), String> {
+    let root = folder
+        .canonicalize()
+        .unwrap_or_else(|_| folder.to_path_buf());
+    let relative = path.strip_prefix(folder).unwrap_or(path);
+
+    let mut current = folder.to_path_buf();
+    for component in relative.components() {
+        current.push(component);
+
+        let metadata = match fs::symlink_metadata(&current) {
+            Ok(metadata) => metadata,
+            Err(_) => break,
+        };
+
+        if metadata.file_type().is_symlink() {
+            let target = current
+                .canonicalize()
+                .map_err(|_| format!("{} is a dangling symlink", current.display()))?;
+            if !target.starts_with(&root) {
+                return Err(format!(
+                    "{} is a symlink pointing outside the download folder",
+                    current.display()
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub fn resolve_dest(folder: &Path, dest: &str) -> Result<PathBuf, String> {
+    let reject = |reason: &str| Err(format!("Rejected unsafe path {:?}: {}", dest, reason));
+    let normalized = dest.replace('\\', "/");
+
+    if normalized.contains('\0') {
+        return reject("contains a NUL byte");
+    }
+    if normalized.starts_with('/') {
+        return reject("absolute path");
+    }
+    let bytes = normalized.as_bytes();
+    if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        return reject("drive letter");
+    }
+
+    let mut path = folder.to_path_buf();
+    for component in normalized.split('/') {
+        match component {
+            "" | "." => continue,
+            ".." => return reject("parent directory reference"),
+            STATE_DIR if path == folder => return reject("reserved state directory"),
+            c if c.contains(':') => return reject("drive or stream specifier"),
+            c => path.push(c),
+        }
+    }
+
+    if path == folder {
+        return reject("empty path");
+    }
+
+    match check_symlinks(folder, &path) {
+        Ok(()) => Ok(path),
+        Err(e) => reject(&e),
+    }
+}
+
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_name);
+
+    let mut file = fs::File::create(&tmp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)
+}
+
+pub fn state_dir(folder: &Path) -> PathBuf {
+    folder.join(STATE_DIR)
+}
+
 pub fn get_dir() -> PathBuf {
     loop {
         print!(
@@ -101,3 +392,67 @@ pub fn get_dir() -> PathBuf {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_dest_accepts_relative_paths() {
+        let folder = Path::new("/games/wuwa");
+
+        assert_eq!(
+            resolve_dest(folder, "Client/Binaries/Win64/Client.exe").unwrap(),
+            folder.join("Client/Binaries/Win64/Client.exe")
+        );
+        assert_eq!(
+            resolve_dest(folder, "Client\\Content\\./Paks//a.pak").unwrap(),
+            folder.join("Client/Content/Paks/a.pak")
+        );
+    }
+
+    #[test]
+    fn resolve_dest_rejects_escapes() {
+        let folder = Path::new("/games/wuwa");
+
+        for dest in [
+            "../outside.exe",
+            "Client/../../outside.exe",
+            "..\\outside.exe",
+            "/etc/passwd",
+            "\\\\server\\share\\file",
+            "C:/Windows/file.dll",
+            "c:file.dll",
+            "Client/file.pak:stream",
+            "Client/\0.pak",
+            "",
+            "./",
+            ".wuwa-downloader/journal.jsonl",
+        ] {
+            assert!(
+                resolve_dest(folder, dest).is_err(),
+                "{:?} should be rejected",
+                dest
+            );
+        }
+    }
+
+    #[test]
+    fn state_dir_name_is_only_reserved_at_the_root() {
+        let folder = Path::new("/games/wuwa");
+        assert!(resolve_dest(folder, "Client/.wuwa-downloader/file").is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolve_dest_rejects_symlinks_leaving_the_folder() {
+        let outside = tempfile::tempdir().unwrap();
+        let folder = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink(outside.path(), folder.path().join("Client")).unwrap();
+        std::os::unix::fs::symlink(folder.path().join("Data"), folder.path().join("Link")).unwrap();
+        fs::create_dir(folder.path().join("Data")).unwrap();
+
+        assert!(resolve_dest(folder.path(), "Client/file.pak").is_err());
+        assert!(resolve_dest(folder.path(), "Link/file.pak").is_ok());
+    }
+}
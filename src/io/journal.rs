@@ -0,0 +1,353 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    config::cfg::{Config, DownloadOptions, ResourceItem},
+    io::{
+        file::state_dir,
+        logging::unix_timestamp,
+        snapshot::{InstallSnapshot, InstalledFile},
+    },
+};
+
+const JOURNAL_FILE: &str = "journal.jsonl";
+
+pub type SharedJournal = Arc<Mutex<fs::File>>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileState {
+    Partial,
+    Failed,
+    Finished,
+    Verified,
+}
+
+impl FileState {
+    pub fn is_done(self) -> bool {
+        matches!(self, FileState::Finished | FileState::Verified)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionMode {
+    Full,
+    Update,
+    Staging,
+}
+
+// What a session set out to fetch, so a resume finishes that set instead of re-planning from the index.
+// `removed` only holds stale files the user already agreed to remove.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SessionPlan {
+    pub mode: SessionMode,
+    pub dests: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SessionHeader {
+    config: Config,
+    options: DownloadOptions,
+    plan: SessionPlan,
+    started: u64,
+}
+
+// File lines vastly outnumber the header, so the header lives behind a box.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum JournalEntry {
+    Session(Box<SessionHeader>),
+    File { dest: String, state: FileState },
+    Finished { finished: u64 },
+}
+
+pub struct Session {
+    pub config: Config,
+    pub options: DownloadOptions,
+    pub plan: SessionPlan,
+    pub files: HashMap<String, FileState>,
+}
+
+impl Session {
+    pub fn mode(&self) -> SessionMode {
+        self.plan.mode
+    }
+
+    pub fn pending(&self, resources: Vec<ResourceItem>) -> Vec<ResourceItem> {
+        let planned: HashSet<&str> = self.plan.dests.iter().map(String::as_str).collect();
+
+        resources
+            .into_iter()
+            .filter(|item| planned.contains(item.dest.as_str()))
+            .filter(|item| !self.files.get(&item.dest).is_some_and(|s| s.is_done()))
+            .collect()
+    }
+
+    pub fn stale(&self, previous: Option<&InstallSnapshot>) -> Vec<InstalledFile> {
+        let Some(previous) = previous else {
+            return Vec::new();
+        };
+        let removed: HashSet<&str> = self.plan.removed.iter().map(String::as_str).collect();
+
+        previous
+            .files
+            .iter()
+            .filter(|file| removed.contains(file.dest.as_str()))
+            .cloned()
+            .collect()
+    }
+}
+
+fn journal_path(folder: &Path) -> PathBuf {
+    state_dir(folder).join(JOURNAL_FILE)
+}
+
+fn write_entry(file: &mut fs::File, entry: &JournalEntry) -> Result<(), String> {
+    let mut line =
+        serde_json::to_string(entry).map_err(|e| format!("Journal encode error: {}", e))?;
+    line.push('\n');
+    file.write_all(line.as_bytes())
+        .and_then(|_| file.sync_data())
+        .map_err(|e| format!("Journal write error: {}", e))
+}
+
+pub fn start_journal(
+    folder: &Path,
+    config: &Config,
+    options: &DownloadOptions,
+    plan: SessionPlan,
+) -> Result<SharedJournal, String> {
+    fs::create_dir_all(state_dir(folder))
+        .map_err(|e| format!("Failed to create state directory: {}", e))?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(journal_path(folder))
+        .map_err(|e| format!("Failed to create journal: {}", e))?;
+
+    write_entry(
+        &mut file,
+        &JournalEntry::Session(Box::new(SessionHeader {
+            config: config.clone(),
+            options: options.clone(),
+            plan,
+            started: unix_timestamp(),
+        })),
+    )?;
+
+    Ok(Arc::new(Mutex::new(file)))
+}
+
+pub fn reopen_journal(folder: &Path) -> Result<SharedJournal, String> {
+    let file = OpenOptions::new()
+        .append(true)
+        .open(journal_path(folder))
+        .map_err(|e| format!("Failed to open journal: {}", e))?;
+
+    Ok(Arc::new(Mutex::new(file)))
+}
+
+pub fn load_session(folder: &Path) -> Result<Session, String> {
+    let path = journal_path(folder);
+    let file = fs::File::open(&path)
+        .map_err(|e| format!("No resumable session in {}: {}", folder.display(), e))?;
+
+    let mut session: Option<Session> = None;
+    let mut finished = false;
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|e| format!("Journal read error: {}", e))?;
+
+        // A crash can leave a torn last line behind; everything before it is still valid.
+        let Ok(entry) = serde_json::from_str::<JournalEntry>(&line) else {
+            continue;
+        };
+
+        match entry {
+            JournalEntry::Session(header) => {
+                let SessionHeader {
+                    config,
+                    options,
+                    plan,
+                    ..
+                } = *header;
+                session = Some(Session {
+                    config,
+                    options,
+                    plan,
+                    files: HashMap::new(),
+                });
+                finished = false;
+            }
+            JournalEntry::Finished { .. } => finished = true,
+            JournalEntry::File { dest, state } => {
+                if let Some(session) = session.as_mut() {
+                    session.files.insert(dest, state);
+                }
+            }
+        }
+    }
+
+    // Replaying a finished run would redo its snapshot and stale-file steps against the new install.
+    if finished {
+        return Err(format!(
+            "The last session in {} already finished, there is nothing to resume",
+            folder.display()
+        ));
+    }
+    session.ok_or_else(|| format!("Journal {} has no session header", path.display()))
+}
+
+pub fn finish_journal(journal: &SharedJournal) -> Result<(), String> {
+    let mut file = journal
+        .lock()
+        .map_err(|_| "Journal lock poisoned".to_string())?;
+
+    write_entry(
+        &mut file,
+        &JournalEntry::Finished {
+            finished: unix_timestamp(),
+        },
+    )
+}
+
+pub fn record_file(journal: &SharedJournal, dest: &str, state: FileState) -> Result<(), String> {
+    let mut file = journal
+        .lock()
+        .map_err(|_| "Journal lock poisoned".to_string())?;
+
+    write_entry(
+        &mut file,
+        &JournalEntry::File {
+            dest: dest.to_string(),
+            state,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(dest: &str) -> ResourceItem {
+        ResourceItem {
+            dest: dest.to_string(),
+            md5: None,
+            size: None,
+        }
+    }
+
+    fn session(plan: SessionPlan, files: &[(&str, FileState)]) -> Session {
+        Session {
+            config: Config::default(),
+            options: DownloadOptions::default(),
+            plan,
+            files: files
+                .iter()
+                .map(|(dest, state)| (dest.to_string(), *state))
+                .collect(),
+        }
+    }
+
+    fn dests(items: &[ResourceItem]) -> Vec<&str> {
+        items.iter().map(|item| item.dest.as_str()).collect()
+    }
+
+    #[test]
+    fn pending_is_limited_to_the_planned_set() {
+        let plan = SessionPlan {
+            mode: SessionMode::Update,
+            dests: vec!["a".into(), "b".into()],
+            removed: Vec::new(),
+        };
+        let session = session(plan, &[("a", FileState::Verified)]);
+
+        let pending = session.pending(vec![item("a"), item("b"), item("c")]);
+        assert_eq!(dests(&pending), ["b"]);
+        assert_eq!(session.mode(), SessionMode::Update);
+    }
+
+    #[test]
+    fn stale_files_come_from_the_plan() {
+        let plan = SessionPlan {
+            mode: SessionMode::Update,
+            dests: Vec::new(),
+            removed: vec!["old".into()],
+        };
+        let previous = InstallSnapshot {
+            files: ["old", "kept"]
+                .iter()
+                .map(|dest| InstalledFile {
+                    dest: dest.to_string(),
+                    size: 1,
+                    md5: None,
+                    completed: 0,
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        let stale = session(plan, &[]).stale(Some(&previous));
+        assert_eq!(
+            stale.iter().map(|f| f.dest.as_str()).collect::<Vec<_>>(),
+            ["old"]
+        );
+    }
+
+    #[test]
+    fn session_header_round_trips_the_plan() {
+        let plan = SessionPlan {
+            mode: SessionMode::Staging,
+            dests: vec!["a".into()],
+            removed: Vec::new(),
+        };
+        let entry = JournalEntry::Session(Box::new(SessionHeader {
+            config: session(plan.clone(), &[]).config,
+            options: DownloadOptions::default(),
+            plan,
+            started: 0,
+        }));
+        let line = serde_json::to_string(&entry).unwrap();
+        assert!(line.starts_with(r#"{"kind":"session","#));
+
+        let JournalEntry::Session(header) = serde_json::from_str(&line).unwrap() else {
+            panic!("expected a session header");
+        };
+        assert_eq!(header.plan.mode, SessionMode::Staging);
+        assert_eq!(header.plan.dests, ["a"]);
+    }
+
+    #[test]
+    fn finished_sessions_cannot_be_resumed() {
+        let folder = tempfile::tempdir().unwrap();
+        let plan = SessionPlan {
+            mode: SessionMode::Full,
+            dests: vec!["a".into()],
+            removed: Vec::new(),
+        };
+        let config = session(plan.clone(), &[]).config;
+        let options = DownloadOptions::default();
+
+        let journal = start_journal(folder.path(), &config, &options, plan.clone()).unwrap();
+        record_file(&journal, "a", FileState::Partial).unwrap();
+        let resumed = load_session(folder.path()).unwrap();
+        assert_eq!(resumed.files.get("a"), Some(&FileState::Partial));
+
+        record_file(&journal, "a", FileState::Verified).unwrap();
+        finish_journal(&journal).unwrap();
+        let error = load_session(folder.path()).err().unwrap();
+        assert!(error.contains("already finished"), "{}", error);
+
+        start_journal(folder.path(), &config, &options, plan).unwrap();
+        assert!(load_session(folder.path()).is_ok());
+    }
+}
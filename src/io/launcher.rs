@@ -0,0 +1,62 @@
+use serde_json::{Map, Value, json};
+use std::{fs, path::Path};
+
+use crate::{config::cfg::Config, io::file::write_atomic};
+
+pub const LAUNCHER_CONFIG_FILE: &str = "launcherDownloadConfig.json";
+
+pub fn installed_version(folder: &Path) -> Option<String> {
+    let data = fs::read(folder.join(LAUNCHER_CONFIG_FILE)).ok()?;
+    let config: Value = serde_json::from_slice(&data).ok()?;
+    config
+        .get("version")
+        .and_then(Value::as_str)
+        .filter(|version| !version.is_empty())
+        .map(|version| version.to_string())
+}
+
+fn app_id(config: &Config) -> Option<String> {
+    let urls = config
+        .source
+        .iter()
+        .map(|source| source.url.as_str())
+        .chain(Some(config.index_url.as_str()));
+
+    // Launcher URLs carry the game id as a path segment, e.g. `/launcher/game/G153/...`.
+    urls.flat_map(|url| url.split('/'))
+        .find(|segment| {
+            segment.len() > 1
+                && segment.starts_with('G')
+                && segment[1..].chars().all(|c| c.is_ascii_digit())
+        })
+        .map(|segment| segment.to_string())
+}
+
+pub fn write_launcher_config(folder: &Path, config: &Config) -> Result<(), String> {
+    let Some(version) = config.version.as_deref() else {
+        // Custom sources never carry a version, so there is nothing to tell the launcher.
+        return match config.source {
+            Some(_) => Err("Version unknown, launcher metadata not written".to_string()),
+            None => Ok(()),
+        };
+    };
+    let path = folder.join(LAUNCHER_CONFIG_FILE);
+
+    // Keep fields written by the official launcher that we do not know about.
+    let mut launcher_config = fs::read(&path)
+        .ok()
+        .and_then(|data| serde_json::from_slice::<Map<String, Value>>(&data).ok())
+        .unwrap_or_default();
+
+    launcher_config.insert("version".to_string(), json!(version));
+    launcher_config.insert("reUseVersion".to_string(), json!(""));
+    launcher_config.insert("state".to_string(), json!(""));
+    launcher_config.insert("isPreDownload".to_string(), json!(false));
+    if let Some(app_id) = app_id(config) {
+        launcher_config.insert("appId".to_string(), json!(app_id));
+    }
+
+    let json = serde_json::to_vec(&launcher_config)
+        .map_err(|e| format!("Failed to encode launcher metadata: {}", e))?;
+    write_atomic(&path, &json).map_err(|e| format!("Failed to write launcher metadata: {}", e))
+}
@@ -17,11 +17,15 @@ pub fn setup_logging() -> SharedLogFile {
     ))
 }
 
-pub fn log_error(log_file: &SharedLogFile, message: &str) {
-    let timestamp = SystemTime::now()
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
         .unwrap_or_default()
-        .as_secs();
+        .as_secs()
+}
+
+pub fn log_error(log_file: &SharedLogFile, message: &str) {
+    let timestamp = unix_timestamp();
 
     if let Ok(mut file) = log_file.lock() {
         let _ = writeln!(file, "[{}] ERROR: {}", timestamp, message);
@@ -1,4 +1,11 @@
+pub mod cache;
+pub mod compress;
 pub mod console;
+pub mod detect;
+pub mod disk;
 pub mod file;
+pub mod journal;
+pub mod launcher;
 pub mod logging;
+pub mod snapshot;
 pub mod util;
@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{
+    config::cfg::{Config, ConfigSource, ResourceItem},
+    io::{
+        file::{resolve_dest, state_dir, write_atomic},
+        logging::unix_timestamp,
+    },
+};
+
+const SNAPSHOT_FILE: &str = "install.json";
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct InstalledFile {
+    pub dest: String,
+    pub size: u64,
+    pub md5: Option<String>,
+    pub completed: u64,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct InstallSnapshot {
+    pub version: Option<String>,
+    pub source: Option<ConfigSource>,
+    pub index_url: String,
+    pub written: u64,
+    #[serde(default)]
+    pub voice_languages: Option<Vec<String>>,
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    pub files: Vec<InstalledFile>,
+}
+
+fn snapshot_path(folder: &Path) -> PathBuf {
+    state_dir(folder).join(SNAPSHOT_FILE)
+}
+
+fn modified_secs(metadata: &fs::Metadata) -> Option<u64> {
+    metadata
+        .modified()
+        .ok()?
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_secs())
+}
+
+pub fn build_snapshot(
+    config: &Config,
+    resources: &[ResourceItem],
+    folder: &Path,
+    completed: &HashMap<String, u64>,
+) -> Result<InstallSnapshot, String> {
+    let mut files = Vec::with_capacity(resources.len());
+
+    for item in resources {
+        let path = resolve_dest(folder, &item.dest)?;
+        let metadata =
+            fs::metadata(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+        // Files finished by an earlier, resumed run fall back to their modification time.
+        let completed = completed
+            .get(&item.dest)
+            .copied()
+            .or_else(|| modified_secs(&metadata))
+            .unwrap_or_default();
+
+        files.push(InstalledFile {
+            dest: item.dest.clone(),
+            size: metadata.len(),
+            md5: item.md5.clone(),
+            completed,
+        });
+    }
+
+    Ok(InstallSnapshot {
+        version: config.version.clone(),
+        source: config.source.clone(),
+        index_url: config.index_url.clone(),
+        written: unix_timestamp(),
+        voice_languages: None,
+        include: Vec::new(),
+        exclude: Vec::new(),
+        files,
+    })
+}
+
+pub fn write_snapshot(folder: &Path, snapshot: &InstallSnapshot) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(snapshot)
+        .map_err(|e| format!("Failed to encode install snapshot: {}", e))?;
+
+    write_atomic(&snapshot_path(folder), &json)
+        .map_err(|e| format!("Failed to write install snapshot: {}", e))
+}
+
+pub fn load_snapshot(folder: &Path) -> Result<Option<InstallSnapshot>, String> {
+    let path = snapshot_path(folder);
+    let data = match fs::read(&path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+
+    serde_json::from_slice(&data)
+        .map(Some)
+        .map_err(|e| format!("Invalid install snapshot {}: {}", path.display(), e))
+}
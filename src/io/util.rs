@@ -6,7 +6,7 @@ use std::{
     fs, io,
     io::Write,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{Arc, Mutex, Once},
     thread,
     time::{Duration, Instant},
 };
@@ -25,10 +25,15 @@ use crate::{
     },
     download::progress::{DownloadProgress, ProgressDisplay},
     io::{
-        file::{check_existing_file, file_size, get_filename},
-        logging::{SharedLogFile, log_error},
+        disk::start_disk_monitor,
+        file::{check_existing_file, file_size, get_filename, resolve_dest},
+        journal::{FileState, SharedJournal, record_file},
+        logging::{SharedLogFile, log_error, unix_timestamp},
+    },
+    network::{
+        client::{build_download_url, download_file, source_size},
+        models::{IndexFile, from_value_checked},
     },
-    network::client::{build_download_url, download_file},
 };
 
 pub fn format_duration(duration: Duration) -> String {
@@ -67,26 +72,59 @@ fn log_url(url: &str) {
     }
 }
 
-pub fn parse_resources(data: &Value) -> Result<Vec<ResourceItem>, String> {
-    let resources = data
-        .get("resource")
-        .and_then(Value::as_array)
-        .ok_or_else(|| "No resources found in index file".to_string())?;
+pub struct ParsedIndex {
+    pub resources: Vec<ResourceItem>,
+    // Entries without a `dest` cannot be placed anywhere; they are counted so callers can report them.
+    pub skipped: usize,
+    pub warnings: Vec<String>,
+}
+
+pub fn parse_resources(data: &Value) -> Result<ParsedIndex, String> {
+    let (index, warnings): (IndexFile, _) = from_value_checked(data, "index file")?;
+
+    let mut parsed = Vec::with_capacity(index.resource.len());
+    let mut skipped = 0;
+    for entry in index.resource {
+        match entry.dest {
+            Some(dest) if !dest.is_empty() => parsed.push(ResourceItem {
+                dest,
+                md5: entry.md5,
+                size: entry.size,
+            }),
+            _ => skipped += 1,
+        }
+    }
+
+    Ok(ParsedIndex {
+        resources: parsed,
+        skipped,
+        warnings,
+    })
+}
+
+pub fn validate_resources(
+    resources: Vec<ResourceItem>,
+    folder: &Path,
+) -> (Vec<ResourceItem>, Vec<String>) {
+    let mut accepted = Vec::with_capacity(resources.len());
+    let mut rejected = Vec::new();
 
-    let mut parsed = Vec::with_capacity(resources.len());
     for item in resources {
-        if let Some(dest) = item.get("dest").and_then(Value::as_str) {
-            parsed.push(ResourceItem {
-                dest: dest.to_string(),
-                md5: item
-                    .get("md5")
-                    .and_then(Value::as_str)
-                    .map(|md5| md5.to_string()),
-            });
+        match resolve_dest(folder, &item.dest) {
+            Ok(_) => accepted.push(item),
+            Err(e) => rejected.push(e),
         }
     }
 
-    Ok(parsed)
+    (accepted, rejected)
+}
+
+pub fn confirm(prompt: &str) -> bool {
+    print!("{} {} (y/n): ", Status::question(), prompt);
+    io::stdout().flush().unwrap();
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).is_ok() && input.trim().eq_ignore_ascii_case("y")
 }
 
 pub fn ask_concurrency() -> DownloadOptions {
@@ -105,6 +143,7 @@ pub fn ask_concurrency() -> DownloadOptions {
         if trimmed.is_empty() {
             return DownloadOptions {
                 concurrency: default_concurrency,
+                ..Default::default()
             };
         }
 
@@ -113,6 +152,7 @@ pub fn ask_concurrency() -> DownloadOptions {
         {
             return DownloadOptions {
                 concurrency: parsed,
+                ..Default::default()
             };
         }
     }
@@ -125,6 +165,7 @@ pub fn ask_concurrency() -> DownloadOptions {
 
     DownloadOptions {
         concurrency: default_concurrency,
+        ..Default::default()
     }
 }
 
@@ -136,54 +177,50 @@ pub async fn calculate_total_size(
 ) -> (u64, HashMap<String, u64>) {
     let mut total_remaining_size = 0;
     let mut failed_urls = 0;
+    let mut rejected = 0;
     let mut size_hints = HashMap::new();
 
     println!("{} Processing files...", Status::info());
 
     for (i, item) in resources.iter().enumerate() {
+        // An unsafe dest is not a size lookup failure, and probing the CDN for it is pointless.
+        let Ok(local_path) = resolve_dest(folder, &item.dest) else {
+            rejected += 1;
+            continue;
+        };
         let mut found_valid_url = false;
 
         for base_url in &config.zip_bases {
             let url = build_download_url(base_url, &item.dest);
             log_url(&url);
 
-            match client
-                .head(&url)
-                .timeout(Duration::from_secs(15))
-                .send()
-                .await
-            {
-                Ok(response) => {
-                    if let Some(len) = response.headers().get("content-length")
-                        && let Ok(len_str) = len.to_str()
-                        && let Ok(total_size) = len_str.parse::<u64>()
-                    {
-                        let local_path = folder.join(item.dest.replace('\\', "/"));
-                        let local_size = file_size(&local_path).await;
-                        let remaining = if local_size < total_size {
-                            // Conservative estimate: partial files may still require full
-                            // redownload when range requests are unsupported.
-                            total_size
-                        } else if local_size > total_size {
-                            total_size
-                        } else if let Some(md5) = item.md5.as_deref() {
-                            if check_existing_file(&local_path, Some(md5), Some(total_size)).await {
-                                0
-                            } else {
-                                total_size
-                            }
-                        } else {
+            match source_size(client, &url).await {
+                Ok(Some(total_size)) => {
+                    let local_size = file_size(&local_path).await;
+                    let remaining = if local_size < total_size {
+                        // Conservative estimate: partial files may still require full
+                        // redownload when range requests are unsupported.
+                        total_size
+                    } else if local_size > total_size {
+                        total_size
+                    } else if let Some(md5) = item.md5.as_deref() {
+                        if check_existing_file(&local_path, Some(md5), Some(total_size)).await {
                             0
-                        };
-
-                        size_hints.insert(item.dest.clone(), total_size);
-                        total_remaining_size += remaining;
-                        found_valid_url = true;
-                        break;
-                    }
+                        } else {
+                            total_size
+                        }
+                    } else {
+                        0
+                    };
+
+                    size_hints.insert(item.dest.clone(), total_size);
+                    total_remaining_size += remaining;
+                    found_valid_url = true;
+                    break;
                 }
+                Ok(None) => {}
                 Err(e) => {
-                    println!("{} Failed to HEAD {}: {}", Status::warning(), url, e);
+                    println!("{} Failed to get size of {}: {}", Status::warning(), url, e);
                 }
             }
         }
@@ -214,6 +251,13 @@ pub async fn calculate_total_size(
             failed_urls
         );
     }
+    if rejected > 0 {
+        println!(
+            "{} Skipped {} files with unsafe paths",
+            Status::warning(),
+            rejected
+        );
+    }
 
     println!(
         "{} Estimated remaining download size: {}",
@@ -229,13 +273,6 @@ pub async fn calculate_total_size(
     (total_remaining_size, size_hints)
 }
 
-pub fn get_version(data: &Value, category: &str, version: &str) -> Result<String, String> {
-    data[category][version]
-        .as_str()
-        .map(|s| s.to_string())
-        .ok_or_else(|| format!("Missing {} URL", version))
-}
-
 pub fn exit_with_error(log_file: &SharedLogFile, error: &str) -> ! {
     log_error(log_file, error);
 
@@ -261,13 +298,14 @@ pub fn track_progress(
     let progress = DownloadProgress {
         total_bytes: Arc::new(std::sync::atomic::AtomicU64::new(total_size)),
         downloaded_bytes: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+        paused: Arc::new(std::sync::atomic::AtomicBool::new(false)),
         start_time: Instant::now(),
     };
 
     (should_stop, success, progress)
 }
 
-#[allow(unused_variables)]
+#[allow(unused_variables, clippy::manual_checked_ops)]
 pub fn start_title_thread(
     should_stop: Arc<std::sync::atomic::AtomicBool>,
     success: Arc<std::sync::atomic::AtomicUsize>,
@@ -331,113 +369,176 @@ pub fn start_title_thread(
     })
 }
 
+static INTERRUPT_FLAG: Mutex<Option<Arc<std::sync::atomic::AtomicBool>>> = Mutex::new(None);
+static CTRLC_HANDLER: Once = Once::new();
+
+// Every run gets a fresh stop flag, but the handler itself can only be installed once.
 pub fn setup_ctrlc(should_stop: Arc<std::sync::atomic::AtomicBool>) {
-    ctrlc::set_handler(move || {
-        should_stop.store(true, std::sync::atomic::Ordering::SeqCst);
+    if let Ok(mut flag) = INTERRUPT_FLAG.lock() {
+        *flag = Some(should_stop);
+    }
+
+    CTRLC_HANDLER.call_once(|| {
+        ctrlc::set_handler(|| {
+            if let Some(flag) = INTERRUPT_FLAG.lock().ok().and_then(|flag| flag.clone()) {
+                flag.store(true, std::sync::atomic::Ordering::SeqCst);
+            }
 
-        #[cfg(windows)]
-        clear().unwrap();
+            #[cfg(windows)]
+            clear().unwrap();
 
-        println!("\n{} Download interrupted by user", Status::warning());
-    })
-    .unwrap();
+            println!("\n{} Download interrupted by user", Status::warning());
+        })
+        .unwrap();
+    });
+}
+
+// One install's share of a download pass; targets in the same pass share one pool.
+pub struct DownloadTarget {
+    pub config: Arc<Config>,
+    pub folder: PathBuf,
+    pub journal: SharedJournal,
+    pub options: Arc<DownloadOptions>,
+    pub resources: Vec<ResourceItem>,
+    pub size_hints: Arc<HashMap<String, u64>>,
 }
 
-#[allow(clippy::too_many_arguments)]
 pub async fn download_resources(
     client: Arc<Client>,
-    config: Arc<Config>,
-    resources: Vec<ResourceItem>,
-    size_hints: Arc<HashMap<String, u64>>,
-    folder: PathBuf,
+    targets: Vec<DownloadTarget>,
     log_file: SharedLogFile,
     should_stop: Arc<std::sync::atomic::AtomicBool>,
     progress: DownloadProgress,
     success: Arc<std::sync::atomic::AtomicUsize>,
-    options: DownloadOptions,
-) {
-    let concurrency = options.concurrency.max(1);
+) -> Vec<HashMap<String, u64>> {
+    let Some(first) = targets.first() else {
+        return Vec::new();
+    };
+    let concurrency = first.options.concurrency.max(1);
     let total_size = progress
         .total_bytes
         .load(std::sync::atomic::Ordering::SeqCst);
 
     let display = Arc::new(ProgressDisplay::new(concurrency, total_size));
+    // The size preflight clears the screen, so this is where the chosen order stays visible.
+    display
+        .total_bar
+        .set_message(format!("order: {}", first.options.order.to_string().cyan()));
     let semaphore = Arc::new(Semaphore::new(concurrency));
     let mut handles = Vec::new();
+    let disk_monitor = start_disk_monitor(
+        targets.iter().map(|target| target.folder.clone()).collect(),
+        should_stop.clone(),
+        progress.clone(),
+        display.total_bar.clone(),
+    );
+    let target_count = targets.len();
+
+    'targets: for (index, target) in targets.into_iter().enumerate() {
+        let DownloadTarget {
+            config,
+            folder,
+            journal,
+            options,
+            resources,
+            size_hints,
+        } = target;
+
+        for item in resources {
+            progress.wait_while_paused(&should_stop).await;
+            if should_stop.load(std::sync::atomic::Ordering::SeqCst) {
+                break 'targets;
+            }
 
-    for item in resources {
-        if should_stop.load(std::sync::atomic::Ordering::SeqCst) {
-            break;
-        }
+            let permit = match semaphore.clone().acquire_owned().await {
+                Ok(permit) => permit,
+                Err(_) => break 'targets,
+            };
 
-        let permit = match semaphore.clone().acquire_owned().await {
-            Ok(permit) => permit,
-            Err(_) => break,
-        };
+            let slot = display.slot_pool.acquire_slot().await;
+
+            let client = client.clone();
+            let config = config.clone();
+            let folder = folder.clone();
+            let log_file = log_file.clone();
+            let journal = journal.clone();
+            let options = options.clone();
+            let should_stop = should_stop.clone();
+            let progress = progress.clone();
+            let success = success.clone();
+            let size_hints = size_hints.clone();
+            let display = display.clone();
+
+            let handle = tokio::spawn(async move {
+                let task_bar = display.slot_pool.bar(slot);
+                let filename = get_filename(&item.dest);
+                let expected_size = size_hints.get(&item.dest).copied();
+
+                task_bar.set_message(format!("downloading {}", filename.clone().cyan()));
+                task_bar.set_position(0);
+                if let Some(size) = expected_size {
+                    task_bar.set_length(size);
+                } else {
+                    task_bar.set_length(0);
+                }
 
-        let slot = display.slot_pool.acquire_slot().await;
-
-        let client = client.clone();
-        let config = config.clone();
-        let folder = folder.clone();
-        let log_file = log_file.clone();
-        let should_stop = should_stop.clone();
-        let progress = progress.clone();
-        let success = success.clone();
-        let size_hints = size_hints.clone();
-        let display = display.clone();
-
-        let handle = tokio::spawn(async move {
-            let task_bar = display.slot_pool.bar(slot);
-            let filename = get_filename(&item.dest);
-            let expected_size = size_hints.get(&item.dest).copied();
-
-            task_bar.set_message(format!("downloading {}", filename.clone().cyan()));
-            task_bar.set_position(0);
-            if let Some(size) = expected_size {
-                task_bar.set_length(size);
-            } else {
-                task_bar.set_length(0);
-            }
+                let ok = download_file(
+                    &client,
+                    &config,
+                    &item.dest,
+                    &folder,
+                    item.md5.as_deref(),
+                    expected_size,
+                    &options,
+                    &log_file,
+                    &should_stop,
+                    &progress,
+                    &display.total_bar,
+                    &task_bar,
+                )
+                .await;
+
+                let state = if ok {
+                    success.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    task_bar.set_message(format!("done {}", filename.green()));
+                    if item.md5.is_some() {
+                        FileState::Verified
+                    } else {
+                        FileState::Finished
+                    }
+                } else if should_stop.load(std::sync::atomic::Ordering::SeqCst) {
+                    task_bar.set_message(format!("stopped {}", filename.yellow()));
+                    FileState::Partial
+                } else {
+                    task_bar.set_message(format!("failed {}", filename.red()));
+                    FileState::Failed
+                };
+
+                if let Err(e) = record_file(&journal, &item.dest, state) {
+                    log_error(&log_file, &e);
+                }
 
-            let ok = download_file(
-                &client,
-                &config,
-                &item.dest,
-                &folder,
-                item.md5.as_deref(),
-                expected_size,
-                &log_file,
-                &should_stop,
-                &progress,
-                &display.total_bar,
-                &task_bar,
-            )
-            .await;
-
-            if ok {
-                success.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-                task_bar.set_message(format!("done {}", filename.green()));
-            } else if should_stop.load(std::sync::atomic::Ordering::SeqCst) {
-                task_bar.set_message(format!("stopped {}", filename.yellow()));
-            } else {
-                task_bar.set_message(format!("failed {}", filename.red()));
-            }
+                task_bar.set_position(0);
+                task_bar.set_length(0);
+                task_bar.set_message("idle");
 
-            task_bar.set_position(0);
-            task_bar.set_length(0);
-            task_bar.set_message("idle");
+                display.slot_pool.release_slot(slot).await;
+                drop(permit);
 
-            display.slot_pool.release_slot(slot).await;
-            drop(permit);
-        });
+                ok.then(|| (index, item.dest, unix_timestamp()))
+            });
 
-        handles.push(handle);
+            handles.push(handle);
+        }
     }
 
+    let mut completed = vec![HashMap::new(); target_count];
     for handle in handles {
-        let _ = handle.await;
+        if let Ok(Some((index, dest, finished_at))) = handle.await {
+            completed[index].insert(dest, finished_at);
+        }
     }
+    disk_monitor.abort();
 
     for slot in 0..display.slot_pool.len() {
         display.slot_pool.bar(slot).finish_with_message("idle");
@@ -451,4 +552,6 @@ pub async fn download_resources(
             bytes_to_human(progress.downloaded())
         ));
     }
+
+    completed
 }
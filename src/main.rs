@@ -1,5 +1,13 @@
 use colored::*;
 use reqwest::Client;
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize},
+    },
+};
 
 #[cfg(not(target_os = "windows"))]
 use std::process::Command;
@@ -33,17 +41,50 @@ fn enable_ansi_support() {
 }
 
 use wuwa_downloader::{
-    config::status::Status,
+    config::{
+        args::{Args, Command as CliCommand},
+        cfg::{Config, DownloadOptions, ResourceItem},
+        status::Status,
+    },
+    download::{
+        backup::rollback,
+        dedupe::{link_from_roots, link_known_files, print_link_summary},
+        filter::{ResourceFilter, print_filter_summary, record_patterns, remember_patterns},
+        order::order_resources,
+        patch::apply_patch,
+        plan::{build_plan, print_plan, write_plan},
+        progress::DownloadProgress,
+        staging::{
+            apply_staged, apply_update, prepare_staging, prepare_update, staging_dir, update_dir,
+            write_staged_manifest,
+        },
+        stray::{
+            CleanAction, check_install_folder, clean_stray_files, find_stray_files,
+            print_stray_files,
+        },
+        update::{plan_update, print_update_plan, remove_stale_files},
+        voice::choose_voice_languages,
+    },
     io::{
-        console::print_results,
-        file::get_dir,
-        logging::setup_logging,
+        cache::evict_cache,
+        console::{pause_before_exit, print_results, print_summary},
+        detect::{InstallAction, InstallSource, detect_install, identify_install, report_install},
+        disk::{check_free_space, check_shared_space},
+        file::{get_dir, resolve_dest},
+        journal::{
+            Session, SessionMode, SessionPlan, SharedJournal, finish_journal, load_session,
+            reopen_journal, start_journal,
+        },
+        launcher::write_launcher_config,
+        logging::{SharedLogFile, log_error, setup_logging},
+        snapshot::{InstallSnapshot, InstalledFile, build_snapshot, load_snapshot, write_snapshot},
         util::{
-            ask_concurrency, calculate_total_size, download_resources, exit_with_error,
-            parse_resources, setup_ctrlc, start_title_thread, track_progress,
+            DownloadTarget, ParsedIndex, ask_concurrency, bytes_to_human, calculate_total_size,
+            confirm, download_resources, exit_with_error, parse_resources, setup_ctrlc,
+            start_title_thread, track_progress, validate_resources,
         },
     },
-    network::client::{fetch_index, get_config},
+    network::client::{fetch_index, get_config, get_target_configs},
 };
 
 #[tokio::main]
@@ -62,79 +103,1091 @@ async fn main() {
     let log_file = setup_logging();
     let client = Client::new();
 
-    let config = match get_config(&client).await {
-        Ok(c) => c,
+    let args = match Args::parse() {
+        Ok(args) => args,
         Err(e) => exit_with_error(&log_file, &e),
     };
 
-    let folder = get_dir();
-    let options = ask_concurrency();
+    if let CliCommand::Apply(path) = &args.command {
+        let folder = path
+            .clone()
+            .unwrap_or_else(|| std::env::current_dir().unwrap());
+        let mut options = DownloadOptions::default();
+        args.apply_to(&mut options);
+        let keep_backups = options.backup.then_some(options.keep_backups);
+
+        match apply_staged(&client, &folder, keep_backups, &log_file).await {
+            Ok(applied) => {
+                println!(
+                    "{} Applied {} staged files",
+                    Status::success(),
+                    applied.to_string().green()
+                );
+                pause_before_exit();
+            }
+            Err(e) => exit_with_error(&log_file, &e),
+        }
+        return;
+    }
+
+    if let CliCommand::Rollback(path) = &args.command {
+        let folder = path
+            .clone()
+            .unwrap_or_else(|| std::env::current_dir().unwrap());
+
+        match rollback(&folder) {
+            Ok(manifest) => {
+                println!(
+                    "{} Restored version {}: {} files restored, {} added files removed",
+                    Status::success(),
+                    manifest
+                        .snapshot
+                        .version
+                        .as_deref()
+                        .unwrap_or("unknown")
+                        .cyan(),
+                    manifest.replaced.len().to_string().green(),
+                    manifest.added.len().to_string().green()
+                );
+                pause_before_exit();
+            }
+            Err(e) => exit_with_error(&log_file, &e),
+        }
+        return;
+    }
+
+    if let CliCommand::Clean(path) = &args.command {
+        let config = match get_config(
+            &client,
+            args.release.as_deref(),
+            &args.catalogs,
+            args.dry_run,
+        )
+        .await
+        {
+            Ok(c) => c,
+            Err(e) => exit_with_error(&log_file, &e),
+        };
+        let folder = path.clone().unwrap_or_else(get_dir);
+
+        let data = match fetch_index(&client, &config, &log_file).await {
+            Ok(data) => data,
+            Err(e) => exit_with_error(&log_file, &e),
+        };
+        let resources = match parse_resources(&data) {
+            Ok(index) => index.resources,
+            Err(e) => exit_with_error(&log_file, &e),
+        };
+        let (resources, _) = validate_resources(resources, &folder);
+        if let Err(e) = check_install_folder(&folder, &resources) {
+            exit_with_error(&log_file, &e);
+        }
+
+        let stray = match find_stray_files(&folder, &resources, &args.keep) {
+            Ok(stray) => stray,
+            Err(e) => exit_with_error(&log_file, &e),
+        };
+        print_stray_files(&stray);
+
+        let action = match args.clean_action {
+            _ if stray.is_empty() => None,
+            Some(CleanAction::Delete)
+                if !args.yes && !confirm(&format!("Permanently delete {} files?", stray.len())) =>
+            {
+                None
+            }
+            Some(action) => Some(action),
+            None => confirm("Move them to quarantine?").then_some(CleanAction::Quarantine),
+        };
+        if let Some(action) = action {
+            let (cleaned, quarantine) = clean_stray_files(&folder, &stray, action, &log_file);
+            match quarantine {
+                Some(quarantine) => println!(
+                    "{} Moved {} files to {}",
+                    Status::success(),
+                    cleaned.to_string().green(),
+                    quarantine.display().to_string().cyan()
+                ),
+                None => println!(
+                    "{} Deleted {} files",
+                    Status::success(),
+                    cleaned.to_string().green()
+                ),
+            }
+        }
+
+        pause_before_exit();
+        return;
+    }
+
+    if !args.targets.is_empty() {
+        let specs: Vec<&str> = args.targets.iter().map(|(spec, _)| spec.as_str()).collect();
+        let configs = match get_target_configs(&client, &specs, &args.catalogs, args.dry_run).await
+        {
+            Ok(configs) => configs,
+            Err(e) => exit_with_error(&log_file, &e),
+        };
+
+        let mut options = ask_concurrency();
+        args.apply_to(&mut options);
+
+        // Every target is planned first, so one pool can fetch them all and shared files only once.
+        let (should_stop, success, progress) = track_progress(0);
+        let mut outcomes: Vec<(usize, &str, Result<RunOutcome, String>)> = Vec::new();
+        let mut runs = Vec::new();
+        let mut run_specs = Vec::new();
+        for (index, ((spec, folder), config)) in args.targets.iter().zip(configs).enumerate() {
+            if should_stop.load(std::sync::atomic::Ordering::SeqCst) {
+                break;
+            }
+
+            println!(
+                "\n{} Target {} -> {}",
+                Status::info(),
+                spec.cyan(),
+                folder.display().to_string().cyan()
+            );
+            let prepared = match config {
+                Ok(config) => {
+                    prepare_target(
+                        &client,
+                        &args,
+                        spec,
+                        folder,
+                        config,
+                        &options,
+                        &should_stop,
+                        &log_file,
+                    )
+                    .await
+                }
+                Err(e) => Err(e),
+            };
+            match prepared {
+                Ok(Some(run)) => {
+                    runs.push(run);
+                    run_specs.push((index, spec.as_str()));
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    log_error(&log_file, &e);
+                    println!("{} {}", Status::error(), e);
+                    outcomes.push((index, spec, Err(e)));
+                }
+            }
+        }
+
+        let deferred = defer_duplicates(&mut runs);
+        if deferred > 0 {
+            println!(
+                "{} {} files appear more than once and will be fetched once, then linked",
+                Status::info(),
+                deferred.to_string().cyan()
+            );
+        }
+
+        let mut measured = Vec::new();
+        let mut measured_specs = Vec::new();
+        for (mut run, (index, spec)) in runs.into_iter().zip(run_specs) {
+            println!("\n{} {}", Status::info(), spec.cyan());
+            match measure_download(&client, &mut run, &should_stop, &progress, &log_file).await {
+                Ok(()) => {
+                    measured.push(run);
+                    measured_specs.push((index, spec));
+                }
+                Err(e) => {
+                    log_error(&log_file, &e);
+                    println!("{} {}", Status::error(), e);
+                    outcomes.push((index, spec, Err(e)));
+                }
+            }
+        }
+
+        let short: Vec<(Vec<usize>, String)> = check_shared_space(
+            &measured
+                .iter()
+                .map(|run| (run.target.as_path(), run.required))
+                .collect::<Vec<_>>(),
+        );
+        for (_, e) in &short {
+            log_error(&log_file, e);
+            println!("{} {}", Status::warning(), e);
+        }
+        if !short.is_empty() && !confirm("Continue anyway?") {
+            let cancelled: HashSet<usize> =
+                short.into_iter().flat_map(|(members, _)| members).collect();
+            let mut kept = Vec::new();
+            let mut kept_specs = Vec::new();
+            for (position, (run, (index, spec))) in
+                measured.into_iter().zip(measured_specs).enumerate()
+            {
+                if cancelled.contains(&position) {
+                    progress
+                        .total_bytes
+                        .fetch_sub(run.required, std::sync::atomic::Ordering::SeqCst);
+                    let e = "Download cancelled: not enough free disk space".to_string();
+                    outcomes.push((index, spec, Err(e)));
+                } else {
+                    kept.push(run);
+                    kept_specs.push((index, spec));
+                }
+            }
+            measured = kept;
+            measured_specs = kept_specs;
+        }
+
+        if !measured.is_empty() {
+            let (fetched, interrupted) = fetch_downloads(
+                &client,
+                &mut measured,
+                &should_stop,
+                &success,
+                &progress,
+                &log_file,
+            )
+            .await;
+            for ((run, fetched), (index, spec)) in
+                measured.into_iter().zip(fetched).zip(measured_specs)
+            {
+                let outcome = finish_download(run, fetched, interrupted, &log_file);
+                outcomes.push((index, spec, Ok(outcome)));
+            }
+        }
+
+        outcomes.sort_by_key(|(index, _, _)| *index);
+        for (_, spec, outcome) in &outcomes {
+            println!("\n{} {}", Status::info(), spec.cyan());
+            match outcome {
+                Ok(outcome) => print_summary(outcome.success, outcome.total, &outcome.target),
+                Err(e) => println!("{} Target failed: {}", Status::error(), e),
+            }
+        }
+        if !args.dry_run {
+            pause_before_exit();
+        }
+        return;
+    }
+
+    let (config, folder, options, session, previous) = match &args.command {
+        CliCommand::Download => {
+            let config = match get_config(
+                &client,
+                args.release.as_deref(),
+                &args.catalogs,
+                args.dry_run,
+            )
+            .await
+            {
+                Ok(c) => c,
+                Err(e) => exit_with_error(&log_file, &e),
+            };
+
+            let folder = get_dir();
+            let detected = identify_install(
+                &client,
+                &config,
+                &folder,
+                detect_install(&folder),
+                &log_file,
+            )
+            .await;
+            let action = report_install(detected.as_ref(), config.version.as_deref());
+
+            let mut options = ask_concurrency();
+            args.apply_to(&mut options);
+            options.staging = config.is_predownload();
+
+            // Predownloads only stage what differs from the playable install next to them.
+            let previous = match detected.map(|detected| detected.source) {
+                Some(InstallSource::Snapshot(snapshot) | InstallSource::Sampled(snapshot))
+                    if options.staging =>
+                {
+                    Some(snapshot)
+                }
+                Some(InstallSource::Snapshot(snapshot) | InstallSource::Sampled(snapshot))
+                    if action == InstallAction::Update
+                        && confirm("Update only the files that changed?") =>
+                {
+                    Some(snapshot)
+                }
+                _ => {
+                    if action == InstallAction::Repair {
+                        println!(
+                            "{} Repair: every existing file will be verified",
+                            Status::info()
+                        );
+                    }
+                    None
+                }
+            };
+
+            (config, folder, options, None, previous)
+        }
+        CliCommand::Resume(path) => {
+            let folder = path
+                .clone()
+                .unwrap_or_else(|| std::env::current_dir().unwrap());
+            let session = match load_session(&folder) {
+                Ok(session) => session,
+                Err(e) => exit_with_error(&log_file, &e),
+            };
+            // A session is marked finished before the snapshot is rewritten and finished sessions are
+            // refused, so the snapshot still describes the install the session started from.
+            let previous = match session.mode() {
+                SessionMode::Full => None,
+                SessionMode::Update | SessionMode::Staging => load_snapshot(&folder).ok().flatten(),
+            };
+
+            (
+                session.config.clone(),
+                folder,
+                session.options.clone(),
+                Some(session),
+                previous,
+            )
+        }
+        CliCommand::Update(path) => {
+            let config = match get_config(
+                &client,
+                args.release.as_deref(),
+                &args.catalogs,
+                args.dry_run,
+            )
+            .await
+            {
+                Ok(c) => c,
+                Err(e) => exit_with_error(&log_file, &e),
+            };
+
+            let folder = path.clone().unwrap_or_else(get_dir);
+            let previous = match load_snapshot(&folder) {
+                Ok(Some(snapshot)) => snapshot,
+                Ok(None) => exit_with_error(
+                    &log_file,
+                    &format!(
+                        "No install snapshot found in {}, run a full download first",
+                        folder.display()
+                    ),
+                ),
+                Err(e) => exit_with_error(&log_file, &e),
+            };
+
+            let mut options = ask_concurrency();
+            args.apply_to(&mut options);
+            options.staging = config.is_predownload();
+            (config, folder, options, None, Some(previous))
+        }
+        CliCommand::Apply(_) | CliCommand::Rollback(_) | CliCommand::Clean(_) => unreachable!(),
+    };
 
     #[cfg(windows)]
     clear().unwrap();
     #[cfg(not(target_os = "windows"))]
     Command::new("clear").status().unwrap();
 
+    let job = DownloadJob {
+        config,
+        folder,
+        options,
+        session,
+        previous,
+        plan_file: args.plan_file.clone(),
+        reuse_filters: !args.no_filters,
+    };
+
+    // Only a dry run ends without an outcome, and it is meant for scripts, so it must not wait for Enter.
+    if let Some(outcome) = run_download(&client, args.dry_run, job, &log_file).await {
+        #[cfg(windows)]
+        clear().unwrap();
+
+        print_results(outcome.success, outcome.total, &outcome.target);
+    }
+}
+
+struct DownloadJob {
+    config: Config,
+    folder: PathBuf,
+    options: DownloadOptions,
+    session: Option<Session>,
+    previous: Option<InstallSnapshot>,
+    plan_file: Option<PathBuf>,
+    reuse_filters: bool,
+}
+
+struct RunOutcome {
+    success: usize,
+    total: usize,
+    target: PathBuf,
+}
+
+// A planned run whose journal is open, waiting for the download pass.
+struct PreparedRun {
+    config: Config,
+    folder: PathBuf,
+    target: PathBuf,
+    options: DownloadOptions,
+    resumed: bool,
+    previous: Option<InstallSnapshot>,
+    staged_update: bool,
+    filter: ResourceFilter,
+    install_set: Vec<ResourceItem>,
+    stale: Vec<InstalledFile>,
+    journal: SharedJournal,
+    resources: Vec<ResourceItem>,
+    // Copies of files another item already fetches, linked once that one is done.
+    deferred: Vec<ResourceItem>,
+    size_hints: HashMap<String, u64>,
+    // Bytes still to download, as estimated by the size preflight.
+    required: u64,
+}
+
+struct Fetched {
+    done: usize,
+    total: usize,
+    completed: HashMap<String, u64>,
+}
+
+async fn run_download(
+    client: &Client,
+    dry_run: bool,
+    job: DownloadJob,
+    log_file: &SharedLogFile,
+) -> Option<RunOutcome> {
+    let (should_stop, success, progress) = track_progress(0);
+    let mut run = match prepare_download(client, dry_run, job, &should_stop, log_file).await {
+        Ok(Some(run)) => run,
+        Ok(None) => return None,
+        Err(e) => exit_with_error(log_file, &e),
+    };
+    if let Err(e) = measure_download(client, &mut run, &should_stop, &progress, log_file).await {
+        exit_with_error(log_file, &e);
+    }
+
+    let (fetched, interrupted) = fetch_downloads(
+        client,
+        std::slice::from_mut(&mut run),
+        &should_stop,
+        &success,
+        &progress,
+        log_file,
+    )
+    .await;
+    let fetched = fetched.into_iter().next()?;
+    Some(finish_download(run, fetched, interrupted, log_file))
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn prepare_target(
+    client: &Client,
+    args: &Args,
+    spec: &str,
+    folder: &Path,
+    config: Config,
+    options: &DownloadOptions,
+    should_stop: &Arc<AtomicBool>,
+    log_file: &SharedLogFile,
+) -> Result<Option<PreparedRun>, String> {
+    if !args.dry_run {
+        std::fs::create_dir_all(folder)
+            .map_err(|e| format!("Failed to create {}: {}", folder.display(), e))?;
+    }
+
+    let detected =
+        identify_install(client, &config, folder, detect_install(folder), log_file).await;
+    let action = report_install(detected.as_ref(), config.version.as_deref());
+
+    let mut options = options.clone();
+    options.staging = config.is_predownload();
+    // There is no one to ask in a batch, so an outdated install always takes the update path.
+    let previous = match detected.map(|detected| detected.source) {
+        Some(InstallSource::Snapshot(snapshot) | InstallSource::Sampled(snapshot))
+            if options.staging || action == InstallAction::Update =>
+        {
+            Some(snapshot)
+        }
+        _ => {
+            if action == InstallAction::Repair {
+                println!(
+                    "{} Repair: every existing file will be verified",
+                    Status::info()
+                );
+            }
+            None
+        }
+    };
+
+    let job = DownloadJob {
+        config,
+        folder: folder.to_path_buf(),
+        options,
+        session: None,
+        previous,
+        plan_file: args
+            .plan_file
+            .as_deref()
+            .map(|path| path.with_extension(format!("{}.json", spec.replace(':', "-")))),
+        reuse_filters: !args.no_filters,
+    };
+    prepare_download(client, args.dry_run, job, should_stop, log_file).await
+}
+
+async fn prepare_download(
+    client: &Client,
+    dry_run: bool,
+    job: DownloadJob,
+    should_stop: &Arc<AtomicBool>,
+    log_file: &SharedLogFile,
+) -> Result<Option<PreparedRun>, String> {
+    let DownloadJob {
+        config,
+        folder,
+        mut options,
+        session,
+        previous,
+        plan_file,
+        reuse_filters,
+    } = job;
+
+    // With backups, an update downloads next to the live install and only swaps in once complete.
+    let staged_update = !options.staging && options.backup && previous.is_some();
+    let target = if options.staging {
+        match &session {
+            Some(_) => staging_dir(&folder),
+            None if dry_run => staging_dir(&folder),
+            None => prepare_staging(&folder, config.version.as_deref())?,
+        }
+    } else if staged_update {
+        match &session {
+            Some(_) => update_dir(&folder),
+            None if dry_run => update_dir(&folder),
+            None => prepare_update(&folder)?,
+        }
+    } else {
+        folder.clone()
+    };
+
     println!(
         "\n{} Download folder: {}",
         Status::info(),
         folder.display().to_string().cyan()
     );
+    if options.staging {
+        println!(
+            "{} Staging predownload in: {}",
+            Status::info(),
+            target.display().to_string().cyan()
+        );
+    } else if staged_update {
+        println!(
+            "{} Staging update in: {}",
+            Status::info(),
+            target.display().to_string().cyan()
+        );
+    }
     println!(
-        "{} Concurrency: {}\n",
+        "{} Concurrency: {}, order: {}\n",
         Status::info(),
-        options.concurrency.to_string().cyan()
+        options.concurrency.to_string().cyan(),
+        options.order.to_string().cyan()
     );
 
-    let data = fetch_index(&client, &config, &log_file).await;
-    let resources = match parse_resources(&data) {
-        Ok(resources) => resources,
-        Err(err) => exit_with_error(&log_file, &err),
+    let data = fetch_index(client, &config, log_file).await?;
+    let ParsedIndex {
+        resources,
+        skipped,
+        warnings,
+    } = parse_resources(&data)?;
+    for warning in &warnings {
+        log_error(log_file, warning);
+        println!("{} {}", Status::warning(), warning);
+    }
+    if skipped > 0 {
+        let message = format!("{} index entries without a dest were skipped", skipped);
+        log_error(log_file, &message);
+        println!(
+            "{} {} index entries without a dest were skipped",
+            Status::warning(),
+            skipped.to_string().yellow()
+        );
+    }
+
+    let (resources, rejected) = validate_resources(resources, &target);
+    if !rejected.is_empty() {
+        for reason in &rejected {
+            log_error(log_file, reason);
+            println!("{} {}", Status::error(), reason);
+        }
+        println!(
+            "{} {} index entries were rejected and will not be downloaded\n",
+            Status::warning(),
+            rejected.len().to_string().red()
+        );
+    }
+
+    // The session already recorded the choices, so only fresh runs pick filters and voice languages.
+    if session.is_none() {
+        let remembered = load_snapshot(&folder).ok().flatten();
+        if reuse_filters
+            && let Some(snapshot) = &remembered
+            && remember_patterns(&mut options.filters, snapshot)
+        {
+            println!(
+                "{} Keeping include/exclude filters from the install snapshot (--no-filters to drop them)",
+                Status::info()
+            );
+        }
+        if options.filters.voice_languages.is_none() {
+            options.filters.voice_languages = choose_voice_languages(
+                &resources,
+                remembered.and_then(|snapshot| snapshot.voice_languages),
+            );
+        }
+    }
+
+    let filter = ResourceFilter::new(&options.filters)?;
+    let (resources, filtered) = filter.apply(resources);
+
+    let install_set = resources.clone();
+
+    // A resumed session finishes the set it planned; re-planning against the index could pick another.
+    let update_plan = match &session {
+        Some(_) => None,
+        None => previous.as_ref().map(|previous| {
+            let plan = plan_update(&filter.scope(previous), &resources);
+            print_update_plan(
+                &plan,
+                previous.version.as_deref(),
+                config.version.as_deref(),
+            );
+            plan
+        }),
+    };
+
+    let (resources, stale) = match (&session, &update_plan) {
+        (Some(session), _) => {
+            println!(
+                "{} Resuming session: {} files already finished",
+                Status::info(),
+                session
+                    .files
+                    .values()
+                    .filter(|s| s.is_done())
+                    .count()
+                    .to_string()
+                    .cyan()
+            );
+            (session.pending(resources), session.stale(previous.as_ref()))
+        }
+        (None, Some(plan)) => (plan.to_download(), plan.removed.clone()),
+        (None, None) => (resources, Vec::new()),
+    };
+
+    if filter.is_active() {
+        print_filter_summary(&filtered);
+    }
+
+    if dry_run {
+        let mut to_download = resources;
+        order_resources(&mut to_download, options.order, &Default::default());
+        let plan = build_plan(&config, &to_download, &filtered, &target, options.order).await;
+        print_plan(&plan);
+
+        if let Some(path) = &plan_file {
+            write_plan(path, &plan)?;
+            println!(
+                "{} Plan written to {}",
+                Status::success(),
+                path.display().to_string().cyan()
+            );
+        }
+        return Ok(None);
+    }
+
+    // Asked once up front and recorded in the plan, so `resume` finishes without prompting.
+    let stale = if session.is_none()
+        && !options.staging
+        && !stale.is_empty()
+        && !confirm(&format!(
+            "Remove {} files that are no longer part of this version?",
+            stale.len()
+        )) {
+        Vec::new()
+    } else {
+        stale
+    };
+
+    let journal = match &session {
+        Some(_) => reopen_journal(&folder)?,
+        None => {
+            let plan = SessionPlan {
+                mode: match &previous {
+                    _ if options.staging => SessionMode::Staging,
+                    Some(_) => SessionMode::Update,
+                    None => SessionMode::Full,
+                },
+                dests: resources.iter().map(|item| item.dest.clone()).collect(),
+                removed: stale.iter().map(|file| file.dest.clone()).collect(),
+            };
+            start_journal(&folder, &config, &options, plan)?
+        }
+    };
+
+    // Ctrl-C has to reach the patcher too, so the flag is wired up before any network work starts.
+    setup_ctrlc(should_stop.clone());
+
+    let patch = previous
+        .as_ref()
+        .and_then(|previous| previous.version.as_deref())
+        .and_then(|version| config.patch_for(version));
+    let mut resources = match patch {
+        Some(patch) if !options.staging && !resources.is_empty() => {
+            apply_patch(
+                client,
+                patch,
+                resources,
+                &target,
+                &folder,
+                &options,
+                should_stop,
+                log_file,
+            )
+            .await
+        }
+        _ => resources,
     };
 
+    if !options.link_roots.is_empty() {
+        let (remaining, summary) =
+            link_from_roots(resources, &target, &options.link_roots, &journal, log_file).await;
+        print_link_summary(&summary);
+        resources = remaining;
+    }
+
+    Ok(Some(PreparedRun {
+        config,
+        folder,
+        target,
+        options,
+        resumed: session.is_some(),
+        previous,
+        staged_update,
+        filter,
+        install_set,
+        stale,
+        journal,
+        resources,
+        deferred: Vec::new(),
+        size_hints: HashMap::new(),
+        required: 0,
+    }))
+}
+
+// Only the first item with a given MD5 is downloaded; the others wait to be linked from it.
+fn defer_duplicates(runs: &mut [PreparedRun]) -> usize {
+    let mut claimed = HashSet::new();
+    let mut deferred = 0;
+
+    for run in runs {
+        let (fetch, duplicates): (Vec<_>, Vec<_>) = std::mem::take(&mut run.resources)
+            .into_iter()
+            .partition(|item| match (&item.md5, item.size) {
+                (Some(md5), Some(_)) => claimed.insert(md5.clone()),
+                _ => true,
+            });
+        deferred += duplicates.len();
+        run.resources = fetch;
+        run.deferred = duplicates;
+    }
+
+    deferred
+}
+
+async fn measure_download(
+    client: &Client,
+    run: &mut PreparedRun,
+    should_stop: &AtomicBool,
+    progress: &DownloadProgress,
+    log_file: &SharedLogFile,
+) -> Result<(), String> {
     println!(
         "{} Found {} files to download\n",
         Status::info(),
-        resources.len().to_string().cyan()
+        run.resources.len().to_string().cyan()
     );
-    let total_files = resources.len();
 
-    let (total_size, size_hints) =
-        calculate_total_size(&resources, &client, &config, &folder).await;
-    let (should_stop, success, progress) = track_progress(total_size);
+    // Stopped while patching: skip probing every file's size, the download loop exits right away.
+    let (total_size, size_hints) = if should_stop.load(std::sync::atomic::Ordering::SeqCst) {
+        (0, HashMap::new())
+    } else {
+        calculate_total_size(&run.resources, client, &run.config, &run.target).await
+    };
+    order_resources(&mut run.resources, run.options.order, &size_hints);
+
+    if let Err(e) = check_free_space(&run.target, total_size) {
+        if run.resumed {
+            return Err(e);
+        }
+
+        log_error(log_file, &e);
+        println!("{} {}", Status::warning(), e);
+        if !confirm("Continue anyway?") {
+            return Err("Download cancelled: not enough free disk space".to_string());
+        }
+    }
+    progress
+        .total_bytes
+        .fetch_add(total_size, std::sync::atomic::Ordering::SeqCst);
+    run.size_hints = size_hints;
+    run.required = total_size;
+    Ok(())
+}
+
+async fn fetch_downloads(
+    client: &Client,
+    runs: &mut [PreparedRun],
+    should_stop: &Arc<AtomicBool>,
+    success: &Arc<AtomicUsize>,
+    progress: &DownloadProgress,
+    log_file: &SharedLogFile,
+) -> (Vec<Fetched>, bool) {
+    let totals: Vec<usize> = runs
+        .iter()
+        .map(|run| run.resources.len() + run.deferred.len())
+        .collect();
+
+    // Where each claimed MD5 lands, so deferred copies can be linked from it afterwards.
+    let mut sources = HashMap::new();
+    for run in runs.iter() {
+        for item in &run.resources {
+            if let Some(md5) = &item.md5
+                && let Ok(path) = resolve_dest(&run.target, &item.dest)
+            {
+                sources.entry(md5.clone()).or_insert(path);
+            }
+        }
+    }
 
     let title_thread = start_title_thread(
         should_stop.clone(),
         success.clone(),
         progress.clone(),
-        resources.len(),
+        totals.iter().sum(),
     );
 
-    setup_ctrlc(should_stop.clone());
-
-    download_resources(
-        std::sync::Arc::new(client),
-        std::sync::Arc::new(config),
-        resources,
-        std::sync::Arc::new(size_hints),
-        folder.clone(),
+    let client = Arc::new(client.clone());
+    let targets = runs
+        .iter_mut()
+        .map(|run| DownloadTarget {
+            config: Arc::new(run.config.clone()),
+            folder: run.target.clone(),
+            journal: run.journal.clone(),
+            options: Arc::new(run.options.clone()),
+            resources: std::mem::take(&mut run.resources),
+            size_hints: Arc::new(std::mem::take(&mut run.size_hints)),
+        })
+        .collect();
+    let mut completed = download_resources(
+        client.clone(),
+        targets,
         log_file.clone(),
         should_stop.clone(),
-        progress,
+        progress.clone(),
         success.clone(),
-        options,
     )
     .await;
 
-    should_stop.store(true, std::sync::atomic::Ordering::SeqCst);
+    // Copies whose source did not arrive, or whose dest already exists, go through a normal pass.
+    let mut linked = vec![0; runs.len()];
+    let mut leftovers = Vec::new();
+    for (index, run) in runs.iter_mut().enumerate() {
+        let deferred = std::mem::take(&mut run.deferred);
+        if deferred.is_empty() || should_stop.load(std::sync::atomic::Ordering::SeqCst) {
+            continue;
+        }
+
+        let count = deferred.len();
+        let (remaining, summary) =
+            link_known_files(deferred, &run.target, &sources, &run.journal, log_file).await;
+        if summary.total() > 0 {
+            print_link_summary(&summary);
+        }
+        linked[index] = count - remaining.len();
+        success.fetch_add(linked[index], std::sync::atomic::Ordering::SeqCst);
+
+        if !remaining.is_empty() {
+            leftovers.push((
+                index,
+                DownloadTarget {
+                    config: Arc::new(run.config.clone()),
+                    folder: run.target.clone(),
+                    journal: run.journal.clone(),
+                    options: Arc::new(run.options.clone()),
+                    resources: remaining,
+                    size_hints: Arc::new(HashMap::new()),
+                },
+            ));
+        }
+    }
+
+    if !leftovers.is_empty() && !should_stop.load(std::sync::atomic::Ordering::SeqCst) {
+        let (indexes, targets): (Vec<usize>, Vec<DownloadTarget>) = leftovers.into_iter().unzip();
+        let extra = download_resources(
+            client,
+            targets,
+            log_file.clone(),
+            should_stop.clone(),
+            progress.clone(),
+            success.clone(),
+        )
+        .await;
+        for (index, files) in indexes.into_iter().zip(extra) {
+            completed[index].extend(files);
+        }
+    }
+
+    let interrupted = should_stop.swap(true, std::sync::atomic::Ordering::SeqCst);
     let _ = title_thread.join();
 
-    #[cfg(windows)]
-    clear().unwrap();
+    if let Some(run) = runs.first()
+        && let (Some(cache), Some(limit)) =
+            (run.options.cache_dir.as_deref(), run.options.cache_limit)
+        && cache.is_dir()
+    {
+        match evict_cache(cache, limit) {
+            Ok((evicted, freed)) if evicted > 0 => println!(
+                "{} Evicted {} cached files ({}) to stay under the cache limit",
+                Status::info(),
+                evicted,
+                bytes_to_human(freed)
+            ),
+            Ok(_) => {}
+            Err(e) => log_error(log_file, &e),
+        }
+    }
 
-    print_results(
-        success.load(std::sync::atomic::Ordering::SeqCst),
-        total_files,
-        &folder,
-    );
+    let fetched = completed
+        .into_iter()
+        .zip(linked)
+        .zip(totals)
+        .map(|((completed, linked), total)| Fetched {
+            done: completed.len() + linked,
+            total,
+            completed,
+        })
+        .collect();
+    (fetched, interrupted)
+}
+
+fn finish_download(
+    run: PreparedRun,
+    fetched: Fetched,
+    interrupted: bool,
+    log_file: &SharedLogFile,
+) -> RunOutcome {
+    let PreparedRun {
+        config,
+        folder,
+        target,
+        options,
+        previous,
+        staged_update,
+        filter,
+        install_set,
+        stale,
+        journal,
+        ..
+    } = run;
+    let Fetched {
+        done,
+        total,
+        completed,
+    } = fetched;
+
+    let mut files_at = target.clone();
+    if !interrupted && done == total {
+        if options.staging {
+            match write_staged_manifest(&folder, &config, &install_set, &options.filters) {
+                Ok(manifest) => {
+                    mark_finished(&journal, log_file);
+                    println!(
+                        "{} Staged {} files, run `apply` once the new version is live",
+                        Status::success(),
+                        manifest.staged.len()
+                    );
+                }
+                Err(e) => {
+                    log_error(log_file, &e);
+                    println!("{} {}", Status::warning(), e);
+                }
+            }
+        } else {
+            let applied = match &previous {
+                Some(previous) if staged_update => {
+                    println!("{} Applying update...", Status::info());
+                    let dests: Vec<String> = stale.iter().map(|f| f.dest.clone()).collect();
+                    apply_update(
+                        &folder,
+                        previous,
+                        &install_set,
+                        &dests,
+                        options.keep_backups,
+                        log_file,
+                    )
+                    .map(|_| {
+                        files_at = folder.clone();
+                        dests.len()
+                    })
+                }
+                _ => Ok(remove_stale_files(&folder, &stale, log_file)),
+            };
+
+            match applied {
+                Ok(removed) => {
+                    mark_finished(&journal, log_file);
+                    if removed > 0 {
+                        println!("{} Removed {} stale files", Status::success(), removed);
+                    }
+
+                    let earlier = filter
+                        .is_active()
+                        .then(|| load_snapshot(&folder).ok().flatten())
+                        .flatten();
+                    let snapshot = build_snapshot(&config, &install_set, &folder, &completed)
+                        .and_then(|mut snapshot| {
+                            record_patterns(&options.filters, &mut snapshot);
+                            if let Some(earlier) = &earlier {
+                                filter.carry_over(earlier, &mut snapshot);
+                            }
+                            write_snapshot(&folder, &snapshot)
+                        })
+                        .and_then(|_| write_launcher_config(&folder, &config));
+                    if let Err(e) = snapshot {
+                        log_error(log_file, &e);
+                        println!("{} {}", Status::warning(), e);
+                    }
+                }
+                Err(e) => {
+                    log_error(log_file, &e);
+                    println!("{} {}", Status::error(), e);
+                    println!(
+                        "{} The live install was left unchanged, run `resume` to retry",
+                        Status::warning()
+                    );
+                }
+            }
+        }
+    }
+
+    RunOutcome {
+        success: done,
+        total,
+        target: files_at,
+    }
+}
+
+fn mark_finished(journal: &SharedJournal, log_file: &SharedLogFile) {
+    if let Err(e) = finish_journal(journal) {
+        log_error(log_file, &e);
+        println!("{} {}", Status::warning(), e);
+    }
 }
@@ -0,0 +1,254 @@
+use colored::Colorize;
+use reqwest::Client;
+use serde_json::Value;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use tokio::task::JoinSet;
+
+use crate::{
+    config::status::Status,
+    io::{compress::read_text_file, file::write_atomic},
+    network::{
+        client::{config_version, fetch_json},
+        models::{CatalogFile, CatalogRegion, OrderedMap, from_value},
+    },
+};
+
+const DEFAULT_CATALOG_URL: &str = "https://gist.githubusercontent.com/yuhkix/b8796681ac2cd3bab11b7e8cdc022254/raw/4435fd290c07f7f766a6d2ab09ed3096d83b02e3/wuwa.json";
+const CACHE_FILE: &str = "catalog.json";
+
+pub struct CatalogEntry {
+    pub name: String,
+    pub label: String,
+    pub note: Option<String>,
+    pub index_url: String,
+}
+
+pub struct Catalog {
+    pub entries: Vec<CatalogEntry>,
+}
+
+fn default_label(category: &str, region: &str) -> String {
+    let mut category = category.to_string();
+    if let Some(first) = category.get_mut(..1) {
+        first.make_ascii_uppercase();
+    }
+    format!("{} - {}", category, region.to_ascii_uppercase())
+}
+
+impl Catalog {
+    // Regions map either straight to a launcher URL or to `{ "url", "label", "note" }`.
+    pub fn parse(data: &Value) -> Result<Self, String> {
+        let OrderedMap(categories) = from_value::<CatalogFile>(data, "version catalog")?;
+        let mut entries = Vec::new();
+
+        for (category, OrderedMap(regions)) in categories {
+            for (region, entry) in regions {
+                let (url, label, note) = match entry {
+                    CatalogRegion::Url(url) => (url, None, None),
+                    CatalogRegion::Detailed(release) => (release.url, release.label, release.note),
+                };
+
+                entries.push(CatalogEntry {
+                    name: format!("{}-{}", category, region),
+                    label: label.unwrap_or_else(|| default_label(&category, &region)),
+                    note,
+                    index_url: url,
+                });
+            }
+        }
+
+        if entries.is_empty() {
+            return Err("Version catalog has no entries".to_string());
+        }
+
+        Ok(Self { entries })
+    }
+
+    pub fn find(&self, name: &str) -> Result<&CatalogEntry, String> {
+        self.entries
+            .iter()
+            .find(|entry| entry.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| {
+                let names: Vec<&str> = self.entries.iter().map(|e| e.name.as_str()).collect();
+                format!(
+                    "Unknown release {:?}, available: {}",
+                    name,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+pub async fn probe_versions(client: &Client, entries: &[CatalogEntry]) -> Vec<Option<String>> {
+    let mut probes = JoinSet::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let client = client.clone();
+        let url = entry.index_url.clone();
+        probes.spawn(async move {
+            let version = fetch_json(&client, &url)
+                .await
+                .ok()
+                .and_then(|data| config_version(&data, "default"));
+            (i, version)
+        });
+    }
+
+    let mut versions = vec![None; entries.len()];
+    while let Some(result) = probes.join_next().await {
+        if let Ok((i, version)) = result {
+            versions[i] = version;
+        }
+    }
+    versions
+}
+
+fn cache_path() -> Option<PathBuf> {
+    #[cfg(windows)]
+    let base = env::var_os("LOCALAPPDATA").map(PathBuf::from);
+    #[cfg(not(windows))]
+    let base = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")));
+
+    base.map(|base| base.join("wuwa-downloader").join(CACHE_FILE))
+}
+
+fn read_local(path: &Path) -> Result<Value, String> {
+    let text = read_text_file(path)?;
+    serde_json::from_str(&text).map_err(|e| format!("Invalid JSON in {}: {}", path.display(), e))
+}
+
+async fn load_source(client: &Client, source: &str, dry_run: bool) -> Result<Catalog, String> {
+    let data = fetch_json(client, source).await?;
+    let catalog = Catalog::parse(&data)?;
+
+    // A dry run leaves the cached catalog as it was.
+    if !dry_run
+        && let Some(cache) = cache_path()
+        && let Ok(json) = serde_json::to_vec_pretty(&data)
+    {
+        let _ = write_atomic(&cache, &json);
+    }
+
+    Ok(catalog)
+}
+
+fn load_cached() -> Result<Catalog, String> {
+    let path = cache_path().ok_or("No cache directory available")?;
+    let catalog = Catalog::parse(&read_local(&path)?)?;
+
+    let age_days = fs::metadata(&path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .map(|age| age.as_secs() / 86_400)
+        .unwrap_or_default();
+    println!(
+        "{} Using the cached catalog from {} ({} days old), versions may be outdated",
+        Status::warning(),
+        path.display(),
+        age_days.to_string().yellow()
+    );
+
+    Ok(catalog)
+}
+
+// Sources are tried in order; the last catalog that loaded is the fallback of last resort.
+pub async fn fetch_catalog(
+    client: &Client,
+    sources: &[String],
+    dry_run: bool,
+) -> Result<Catalog, String> {
+    let default = [DEFAULT_CATALOG_URL.to_string()];
+    let sources = if sources.is_empty() {
+        &default[..]
+    } else {
+        sources
+    };
+    let mut errors = Vec::new();
+
+    for source in sources {
+        match load_source(client, source, dry_run).await {
+            Ok(catalog) => return Ok(catalog),
+            Err(e) => {
+                println!(
+                    "{} Catalog source {} failed: {}",
+                    Status::warning(),
+                    source,
+                    e
+                );
+                errors.push(e);
+            }
+        }
+    }
+
+    load_cached().map_err(|cache_error| {
+        format!(
+            "All catalog sources failed ({}); no usable cached catalog: {}",
+            errors.join("; "),
+            cache_error
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parse_keeps_file_order_and_builds_names() {
+        let catalog = Catalog::parse(&json!({
+            "live": {
+                "os": "https://example.com/live-os.json",
+                "cn": {
+                    "url": "https://example.com/live-cn.json",
+                    "label": "Live China",
+                    "note": "mainland servers"
+                }
+            },
+            "beta": { "os": "https://example.com/beta-os.json" }
+        }))
+        .unwrap();
+
+        let names: Vec<&str> = catalog.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["live-os", "live-cn", "beta-os"]);
+        assert_eq!(catalog.entries[0].label, "Live - OS");
+        assert_eq!(catalog.entries[0].note, None);
+        assert_eq!(catalog.entries[1].label, "Live China");
+        assert_eq!(catalog.entries[1].note.as_deref(), Some("mainland servers"));
+        assert_eq!(
+            catalog.entries[2].index_url,
+            "https://example.com/beta-os.json"
+        );
+        assert!(catalog.find("LIVE-CN").is_ok());
+        assert!(catalog.find("live-jp").is_err());
+    }
+
+    #[test]
+    fn parse_reports_where_an_entry_is_wrong() {
+        let error = Catalog::parse(&json!({
+            "live": { "os": { "url": "https://example.com", "lable": "typo" } }
+        }))
+        .err()
+        .unwrap();
+        assert!(error.contains("live.os"), "{}", error);
+        assert!(error.contains("lable"), "{}", error);
+
+        let error = Catalog::parse(&json!({ "live": { "os": 42 } }))
+            .err()
+            .unwrap();
+        assert!(error.contains("live.os"), "{}", error);
+    }
+
+    #[test]
+    fn parse_rejects_empty_catalogs() {
+        assert!(Catalog::parse(&json!({})).is_err());
+        assert!(Catalog::parse(&json!({ "live": {} })).is_err());
+        assert!(Catalog::parse(&json!(["live-os"])).is_err());
+    }
+}
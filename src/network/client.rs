@@ -1,29 +1,36 @@
 use colored::Colorize;
 use indicatif::ProgressBar;
+use percent_encoding::percent_decode_str;
 use reqwest::{Client, StatusCode};
 use serde_json::{Value, from_str};
 #[cfg(not(target_os = "windows"))]
 use std::process::Command;
 use std::{
-    io::{self, Write},
-    path::Path,
+    io::{self, SeekFrom, Write},
+    path::{Path, PathBuf},
     time::Duration,
 };
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
 
 #[cfg(windows)]
 use winconsole::console::clear;
 
-use crate::config::cfg::Config;
+use crate::config::cfg::{Config, ConfigSource, DownloadOptions, PatchSource};
 use crate::config::status::Status;
 use crate::download::progress::DownloadProgress;
-use crate::io::file::{calculate_md5, check_existing_file, file_size, get_filename};
+use crate::io::cache::{restore_from_cache, store_in_cache};
+use crate::io::compress::{decode_text, read_text_file};
+use crate::io::file::{
+    BlockWriter, calculate_md5, check_existing_file, file_size, get_filename, preallocate,
+    resolve_dest, unlink_if_shared,
+};
 use crate::io::logging::{SharedLogFile, log_error};
-use crate::io::util::get_version;
+use crate::network::catalog::{fetch_catalog, probe_versions};
+use crate::network::models::LauncherConfig;
 
-const INDEX_URL: &str = "https://gist.githubusercontent.com/yuhkix/b8796681ac2cd3bab11b7e8cdc022254/raw/4435fd290c07f7f766a6d2ab09ed3096d83b02e3/wuwa.json";
 const MAX_RETRIES: usize = 3;
 const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(10_000);
+const LOCAL_CHUNK_SIZE: usize = 256 * 1024;
 
 enum DownloadAttemptResult {
     Completed,
@@ -34,6 +41,12 @@ enum DownloadAttemptResult {
     Interrupted,
 }
 
+enum IndexAttempt {
+    Fetched(Value),
+    Retryable(String),
+    Failed(String),
+}
+
 enum CdnDownloadResult {
     Success,
     RetryWithoutResume,
@@ -61,67 +74,207 @@ pub fn build_download_url(base_url: &str, dest: &str) -> String {
     )
 }
 
-fn handle_http_error(log_file: &SharedLogFile, error_msg: &str) -> ! {
-    log_error(log_file, error_msg);
-    clear_screen();
+// Anything that is not an http(s) URL is a `file://` URL or a path on disk.
+pub fn local_source(source: &str) -> Option<Result<PathBuf, String>> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        return None;
+    }
+
+    Some(match source.strip_prefix("file://") {
+        Some(rest) => {
+            file_url_path(rest).map_err(|e| format!("Invalid file URL {}: {}", source, e))
+        }
+        None if source.contains("://") => Err(format!("Unsupported URL scheme in {}", source)),
+        None => Ok(PathBuf::from(shellexpand::tilde(source).into_owned())),
+    })
+}
 
-    println!("{} {}", Status::error(), error_msg);
-    println!("\n{} Press Enter to exit...", Status::warning());
-    let _ = io::stdin().read_line(&mut String::new());
-    std::process::exit(1);
+fn has_drive_letter(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    bytes.len() >= 2
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && (bytes.len() == 2 || bytes[2] == b'/')
+}
+
+// `file:///C:/Games` names a drive and `file://nas/share` a UNC share; both are percent-encoded.
+fn file_url_path(rest: &str) -> Result<PathBuf, String> {
+    let (host, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    let path = percent_decode_str(path)
+        .decode_utf8()
+        .map_err(|_| "the path is not valid UTF-8".to_string())?;
+    let host = if host.eq_ignore_ascii_case("localhost") {
+        ""
+    } else {
+        host
+    };
+
+    let drive_path = path.strip_prefix('/').filter(|path| has_drive_letter(path));
+    match (host, drive_path) {
+        ("", _) if path.is_empty() => Err("the path is missing".to_string()),
+        ("", Some(drive_path)) if cfg!(windows) => Ok(PathBuf::from(drive_path)),
+        ("", Some(_)) => Err("drive letters are only valid on Windows".to_string()),
+        ("", None) => Ok(PathBuf::from(path.as_ref())),
+        (host, _) if cfg!(windows) => Ok(PathBuf::from(format!(
+            r"\\{}{}",
+            host,
+            path.replace('/', "\\")
+        ))),
+        (host, _) => Err(format!(
+            "shares on another host ({}) are only supported on Windows, mount it and use the local path",
+            host
+        )),
+    }
 }
 
 async fn decompress_if_gzipped(response: reqwest::Response) -> Result<String, String> {
-    response
-        .text()
+    let data = response
+        .bytes()
         .await
-        .map_err(|e| format!("Error reading response text: {}", e))
+        .map_err(|e| format!("Error reading response body: {}", e))?;
+    decode_text(data.to_vec())
 }
 
-pub async fn fetch_index(client: &Client, config: &Config, log_file: &SharedLogFile) -> Value {
-    println!("{} Fetching index file...", Status::info());
+async fn fetch_text(client: &Client, url: &str) -> Result<String, String> {
+    if let Some(path) = local_source(url) {
+        return read_text_file(&path?);
+    }
+
+    let response = client
+        .get(url)
+        .timeout(Duration::from_secs(30))
+        .send()
+        .await
+        .map_err(|e| format!("Network error: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("Server error: HTTP {}", response.status()));
+    }
+
+    decompress_if_gzipped(response).await
+}
+
+pub async fn fetch_json(client: &Client, url: &str) -> Result<Value, String> {
+    let text = fetch_text(client, url).await?;
+    from_str(&text).map_err(|e| format!("Invalid JSON: {}", e))
+}
+
+// Sizes come from a HEAD request, or from the file itself for local sources.
+pub async fn source_size(client: &Client, url: &str) -> Result<Option<u64>, String> {
+    if let Some(path) = local_source(url) {
+        return tokio::fs::metadata(&path?)
+            .await
+            .map(|metadata| Some(metadata.len()))
+            .map_err(|e| e.to_string());
+    }
+
+    let response = client
+        .head(url)
+        .timeout(Duration::from_secs(15))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(response
+        .headers()
+        .get("content-length")
+        .and_then(|len| len.to_str().ok())
+        .and_then(|len| len.parse().ok()))
+}
+
+async fn fetch_index_once(client: &Client, url: &str) -> IndexAttempt {
+    if let Some(path) = local_source(url) {
+        let path = match path {
+            Ok(path) => path,
+            Err(e) => return IndexAttempt::Failed(e),
+        };
+        return match read_text_file(&path).and_then(|text| {
+            from_str(&text).map_err(|e| format!("Invalid JSON in {}: {}", path.display(), e))
+        }) {
+            Ok(value) => IndexAttempt::Fetched(value),
+            Err(e) => IndexAttempt::Failed(e),
+        };
+    }
 
     let response = match client
-        .get(&config.index_url)
+        .get(url)
         .timeout(Duration::from_secs(30))
         .send()
         .await
     {
         Ok(resp) => resp,
-        Err(e) => {
-            let msg = format!("Error fetching index file: {}", e);
-            handle_http_error(log_file, &msg);
-        }
+        Err(e) => return IndexAttempt::Retryable(format!("Network error: {}", e)),
     };
 
-    if !response.status().is_success() {
-        let msg = format!("Error fetching index file: HTTP {}", response.status());
-        log_error(log_file, &msg);
-        clear_screen();
-
-        println!("{} {}", Status::error(), msg);
-        println!("\n{} Press Enter to exit...", Status::warning());
-        let _ = io::stdin().read_line(&mut String::new());
-        std::process::exit(1);
+    let status = response.status();
+    if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+        return IndexAttempt::Retryable(format!("HTTP error: {}", status));
+    }
+    if !status.is_success() {
+        return IndexAttempt::Failed(format!("HTTP error: {}", status));
     }
 
+    // A truncated body fails to parse, so it is retried like a dropped connection.
     let text = match decompress_if_gzipped(response).await {
-        Ok(t) => t,
-        Err(e) => {
-            let msg = format!("Error processing index file: {}", e);
-            handle_http_error(log_file, &msg);
-        }
+        Ok(text) => text,
+        Err(e) => return IndexAttempt::Retryable(e),
     };
+    match from_str(&text) {
+        Ok(value) => IndexAttempt::Fetched(value),
+        Err(e) => IndexAttempt::Retryable(format!("Invalid JSON: {}", e)),
+    }
+}
 
-    println!("{} Index file downloaded successfully", Status::success());
+pub async fn fetch_index(
+    client: &Client,
+    config: &Config,
+    log_file: &SharedLogFile,
+) -> Result<Value, String> {
+    println!("{} Fetching index file...", Status::info());
 
-    match from_str(&text) {
-        Ok(v) => v,
-        Err(e) => {
-            let msg = format!("Error parsing index file JSON: {}", e);
-            handle_http_error(log_file, &msg);
+    let urls = &config.index_mirrors;
+    let mut errors = Vec::new();
+
+    for (i, url) in urls.iter().enumerate() {
+        let mut retries = MAX_RETRIES;
+
+        while retries > 0 {
+            match fetch_index_once(client, url).await {
+                IndexAttempt::Fetched(value) => {
+                    println!("{} Index file downloaded successfully", Status::success());
+                    return Ok(value);
+                }
+                IndexAttempt::Retryable(err) => {
+                    retries -= 1;
+                    log_error(
+                        log_file,
+                        &format!("CDN {} index attempt failed: {}", i + 1, err),
+                    );
+                    if retries == 0 {
+                        errors.push(format!("CDN {}: {}", i + 1, err));
+                    }
+                }
+                IndexAttempt::Failed(err) => {
+                    log_error(log_file, &format!("CDN {} index failed: {}", i + 1, err));
+                    errors.push(format!("CDN {}: {}", i + 1, err));
+                    break;
+                }
+            }
+        }
+
+        if i + 1 < urls.len() {
+            println!(
+                "{} Index unavailable from CDN {}, trying the next one",
+                Status::warning(),
+                i + 1
+            );
         }
     }
+
+    Err(format!(
+        "Error fetching index file from any CDN: {}",
+        errors.join("; ")
+    ))
 }
 
 async fn remove_partial_file(path: &Path) {
@@ -164,84 +317,197 @@ fn rollback_counted_bytes(
     *counted_bytes_for_file = 0;
 }
 
-#[allow(clippy::too_many_arguments)]
-async fn download_single_file(
+enum Body {
+    Http(reqwest::Response),
+    File(tokio::fs::File),
+}
+
+impl Body {
+    async fn chunk(&mut self) -> Result<Option<Vec<u8>>, String> {
+        match self {
+            Self::Http(response) => response
+                .chunk()
+                .await
+                .map(|chunk| chunk.map(Vec::from))
+                .map_err(|e| e.to_string()),
+            Self::File(file) => {
+                let mut buffer = vec![0; LOCAL_CHUNK_SIZE];
+                let read = file.read(&mut buffer).await.map_err(|e| e.to_string())?;
+                if read == 0 {
+                    return Ok(None);
+                }
+                buffer.truncate(read);
+                Ok(Some(buffer))
+            }
+        }
+    }
+}
+
+async fn open_http(
     client: &Client,
     url: &str,
-    path: &Path,
-    should_stop: &std::sync::atomic::AtomicBool,
-    progress: &DownloadProgress,
-    total_pb: &ProgressBar,
-    task_pb: &ProgressBar,
-    allow_resume: bool,
-    counted_bytes_for_file: &mut u64,
-) -> DownloadAttemptResult {
-    let local_size = file_size(path).await;
-    let use_range = allow_resume && local_size > 0;
-
+    resume_from: Option<u64>,
+) -> Result<(Body, bool), DownloadAttemptResult> {
     let request = client
         .get(url)
         .timeout(DOWNLOAD_TIMEOUT)
         .header("Connection", "keep-alive");
 
-    let request = if use_range {
-        request.header("Range", format!("bytes={}-", local_size))
-    } else {
-        request
+    let request = match resume_from {
+        Some(offset) => request.header("Range", format!("bytes={}-", offset)),
+        None => request,
     };
 
-    let mut response = match request.send().await {
+    let response = match request.send().await {
         Ok(resp) => resp,
-        Err(e) => return DownloadAttemptResult::Retryable(format!("Network error: {}", e)),
+        Err(e) => {
+            return Err(DownloadAttemptResult::Retryable(format!(
+                "Network error: {}",
+                e
+            )));
+        }
     };
 
     if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
-        return DownloadAttemptResult::RangeNotSatisfiable;
+        return Err(DownloadAttemptResult::RangeNotSatisfiable);
     }
 
-    if use_range && response.status() == StatusCode::OK {
+    if resume_from.is_some() && response.status() == StatusCode::OK {
         // Range request was ignored (common when server does not support byte ranges).
-        let _accept_ranges = response
-            .headers()
-            .get("accept-ranges")
-            .and_then(|v| v.to_str().ok())
-            .unwrap_or("");
-        return DownloadAttemptResult::RangeUnsupported;
+        return Err(DownloadAttemptResult::RangeUnsupported);
     }
 
     if !response.status().is_success() && response.status() != StatusCode::PARTIAL_CONTENT {
-        return DownloadAttemptResult::HttpError(format!("HTTP error: {}", response.status()));
+        return Err(DownloadAttemptResult::HttpError(format!(
+            "HTTP error: {}",
+            response.status()
+        )));
+    }
+
+    let append_mode = resume_from.is_some() && response.status() == StatusCode::PARTIAL_CONTENT;
+    Ok((Body::Http(response), append_mode))
+}
+
+// A missing file fails over to the next source at once, like an HTTP error.
+async fn open_local(
+    source: &Path,
+    resume_from: Option<u64>,
+) -> Result<(Body, bool), DownloadAttemptResult> {
+    let mut file = match tokio::fs::File::open(source).await {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(DownloadAttemptResult::HttpError(format!(
+                "Not found: {}",
+                source.display()
+            )));
+        }
+        Err(e) => {
+            return Err(DownloadAttemptResult::Retryable(format!(
+                "Source open error: {}",
+                e
+            )));
+        }
+    };
+
+    if let Some(offset) = resume_from {
+        let len = match file.metadata().await {
+            Ok(metadata) => metadata.len(),
+            Err(e) => {
+                return Err(DownloadAttemptResult::Retryable(format!(
+                    "Source read error: {}",
+                    e
+                )));
+            }
+        };
+        if offset >= len {
+            return Err(DownloadAttemptResult::RangeNotSatisfiable);
+        }
+        if let Err(e) = file.seek(SeekFrom::Start(offset)).await {
+            return Err(DownloadAttemptResult::Retryable(format!(
+                "Source seek error: {}",
+                e
+            )));
+        }
     }
 
-    let append_mode = use_range && response.status() == StatusCode::PARTIAL_CONTENT;
-    let mut options = tokio::fs::OpenOptions::new();
-    options.create(true);
+    Ok((Body::File(file), resume_from.is_some()))
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn download_single_file(
+    client: &Client,
+    url: &str,
+    path: &Path,
+    should_stop: &std::sync::atomic::AtomicBool,
+    progress: &DownloadProgress,
+    total_pb: &ProgressBar,
+    task_pb: &ProgressBar,
+    allow_resume: bool,
+    counted_bytes_for_file: &mut u64,
+    expected_size: Option<u64>,
+    options: &DownloadOptions,
+) -> DownloadAttemptResult {
+    let local_size = file_size(path).await;
+    let use_range = allow_resume && local_size > 0;
 
-    if append_mode {
-        options.append(true);
+    let opened = match local_source(url) {
+        Some(Ok(source)) => open_local(&source, use_range.then_some(local_size)).await,
+        Some(Err(e)) => Err(DownloadAttemptResult::HttpError(e)),
+        None => open_http(client, url, use_range.then_some(local_size)).await,
+    };
+    let (mut body, append_mode) = match opened {
+        Ok(opened) => opened,
+        Err(result) => return result,
+    };
+
+    let mut open_options = tokio::fs::OpenOptions::new();
+    open_options.create(true);
+
+    let start_offset = if append_mode {
+        open_options.append(true);
         task_pb.set_position(local_size);
+        local_size
     } else {
-        options.write(true).truncate(true);
+        open_options.write(true).truncate(true);
         task_pb.set_position(0);
-    }
+        0
+    };
 
-    let mut file = match options.open(path).await {
+    let file = match open_options.open(path).await {
         Ok(file) => file,
         Err(e) => return DownloadAttemptResult::Retryable(format!("File open error: {}", e)),
     };
 
+    let file = match expected_size {
+        Some(size) if size > start_offset => {
+            let file = file.into_std().await;
+            // Best effort: filesystems without allocation support simply fragment as before.
+            let _ = preallocate(&file, size);
+            tokio::fs::File::from_std(file)
+        }
+        _ => file,
+    };
+
+    let mut writer = BlockWriter::new(file, start_offset);
+
     loop {
+        progress.wait_while_paused(should_stop).await;
         if should_stop.load(std::sync::atomic::Ordering::SeqCst) {
+            let _ = writer.flush().await;
             return DownloadAttemptResult::Interrupted;
         }
 
-        let chunk = match response.chunk().await {
+        let chunk = match body.chunk().await {
             Ok(Some(chunk)) => chunk,
             Ok(None) => break,
-            Err(e) => return DownloadAttemptResult::Retryable(format!("Read error: {}", e)),
+            Err(e) => {
+                // Keep what already arrived so the next attempt can resume after it.
+                let _ = writer.flush().await;
+                return DownloadAttemptResult::Retryable(format!("Read error: {}", e));
+            }
         };
 
-        if let Err(e) = file.write_all(&chunk).await {
+        if let Err(e) = writer.write(&chunk).await {
             return DownloadAttemptResult::Retryable(format!("Write error: {}", e));
         }
 
@@ -254,7 +520,7 @@ async fn download_single_file(
             .fetch_add(size, std::sync::atomic::Ordering::SeqCst);
     }
 
-    if let Err(e) = file.flush().await {
+    if let Err(e) = writer.finish(options.fsync).await {
         return DownloadAttemptResult::Retryable(format!("File flush error: {}", e));
     }
 
@@ -274,6 +540,8 @@ async fn try_download_with_cdns(
     task_pb: &ProgressBar,
     allow_resume: bool,
     counted_bytes_for_file: &mut u64,
+    expected_size: Option<u64>,
+    options: &DownloadOptions,
 ) -> CdnDownloadResult {
     let mut saw_range_unsupported = false;
     let mut last_error = "Unknown error".to_string();
@@ -302,6 +570,8 @@ async fn try_download_with_cdns(
                 task_pb,
                 allow_resume,
                 counted_bytes_for_file,
+                expected_size,
+                options,
             )
             .await;
 
@@ -395,6 +665,7 @@ pub async fn download_file(
     folder: &Path,
     expected_md5: Option<&str>,
     expected_size: Option<u64>,
+    options: &DownloadOptions,
     log_file: &SharedLogFile,
     should_stop: &std::sync::atomic::AtomicBool,
     progress: &DownloadProgress,
@@ -406,7 +677,17 @@ pub async fn download_file(
     }
 
     let normalized_dest = dest.replace('\\', "/");
-    let path = folder.join(&normalized_dest);
+    let path = match resolve_dest(folder, dest) {
+        Ok(path) => path,
+        Err(e) => {
+            log_error(log_file, &e);
+            task_pb.set_message(format!(
+                "rejected: {}",
+                get_filename(&normalized_dest).red()
+            ));
+            return false;
+        }
+    };
     let filename = get_filename(&normalized_dest);
     let mut counted_bytes_for_file = 0_u64;
 
@@ -424,6 +705,8 @@ pub async fn download_file(
         return true;
     }
 
+    unlink_if_shared(&path);
+
     if let Some(parent) = path.parent()
         && let Err(e) = tokio::fs::create_dir_all(parent).await
     {
@@ -435,6 +718,26 @@ pub async fn download_file(
         return false;
     }
 
+    if let (Some(cache), Some(md5)) = (options.cache_dir.as_deref(), expected_md5) {
+        match restore_from_cache(cache, md5, expected_size, &path).await {
+            Ok(true) => {
+                let size = file_size(&path).await;
+                task_pb.set_position(size);
+                total_pb.inc(size);
+                progress
+                    .downloaded_bytes
+                    .fetch_add(size, std::sync::atomic::Ordering::SeqCst);
+                task_pb.set_message(format!("from cache: {}", filename.bright_purple()));
+                return true;
+            }
+            Ok(false) => {}
+            Err(e) => log_error(
+                log_file,
+                &format!("Cache error for {}: {}", normalized_dest, e),
+            ),
+        }
+    }
+
     let first_pass = try_download_with_cdns(
         client,
         config,
@@ -447,6 +750,8 @@ pub async fn download_file(
         task_pb,
         true,
         &mut counted_bytes_for_file,
+        expected_size,
+        options,
     )
     .await;
 
@@ -474,6 +779,8 @@ pub async fn download_file(
                 task_pb,
                 false,
                 &mut counted_bytes_for_file,
+                expected_size,
+                options,
             )
             .await
             {
@@ -548,6 +855,8 @@ pub async fn download_file(
                 task_pb,
                 false,
                 &mut counted_bytes_for_file,
+                expected_size,
+                options,
             )
             .await
             {
@@ -580,6 +889,15 @@ pub async fn download_file(
         }
     }
 
+    if let (Some(cache), Some(md5)) = (options.cache_dir.as_deref(), expected_md5)
+        && let Err(e) = store_in_cache(cache, md5, &path, options.cache_limit).await
+    {
+        log_error(
+            log_file,
+            &format!("Cache error for {}: {}", normalized_dest, e),
+        );
+    }
+
     true
 }
 
@@ -613,10 +931,25 @@ pub fn ask_download_mode(_client: &Client) -> Result<String, String> {
     }
 }
 
+// Existing paths (e.g. a NAS share or USB drive) act as a CDN; anything else defaults to https.
+fn normalize_source(input: &str) -> String {
+    if input.starts_with("http://") || input.starts_with("https://") || input.starts_with("file://")
+    {
+        return input.to_string();
+    }
+
+    let path = shellexpand::tilde(input);
+    if Path::new(path.as_ref()).exists() {
+        path.into_owned()
+    } else {
+        format!("https://{}", input)
+    }
+}
+
 pub fn get_custom_config(_client: &Client) -> Result<Config, String> {
     println!("\n{} Custom Version Configuration", Status::info());
 
-    print!("{} Enter resource.json URL: ", Status::question());
+    print!("{} Enter resource.json URL or path: ", Status::question());
     io::stdout()
         .flush()
         .map_err(|e| format!("Failed to flush stdout: {}", e))?;
@@ -631,14 +964,10 @@ pub fn get_custom_config(_client: &Client) -> Result<Config, String> {
         return Err("Resource JSON URL cannot be empty".to_string());
     }
 
-    let index_url = if index_url.starts_with("http://") || index_url.starts_with("https://") {
-        index_url.to_string()
-    } else {
-        format!("https://{}", index_url)
-    };
+    let index_url = normalize_source(index_url);
 
     print!(
-        "{} Enter resource base path URL (ending with /zip): ",
+        "{} Enter resource base path URL or folder (ending with /zip): ",
         Status::question()
     );
     io::stdout()
@@ -655,11 +984,7 @@ pub fn get_custom_config(_client: &Client) -> Result<Config, String> {
         return Err("Resource base path URL cannot be empty".to_string());
     }
 
-    let base_url = if base_url.starts_with("http://") || base_url.starts_with("https://") {
-        base_url
-    } else {
-        format!("https://{}", base_url)
-    };
+    let base_url = normalize_source(&base_url);
 
     let base_url = if base_url.ends_with('/') {
         base_url
@@ -669,101 +994,116 @@ pub fn get_custom_config(_client: &Client) -> Result<Config, String> {
 
     println!("\n{} Configuration loaded successfully", Status::success());
     Ok(Config {
+        index_mirrors: vec![index_url.clone()],
         index_url,
         zip_bases: vec![base_url],
+        ..Default::default()
     })
 }
 
-pub async fn get_config(client: &Client) -> Result<Config, String> {
+pub fn config_version(data: &Value, section: &str) -> Option<String> {
+    let (launcher, _) = LauncherConfig::from_json(data.clone()).ok()?;
+    launcher.version(section)
+}
+
+pub async fn get_config(
+    client: &Client,
+    release: Option<&str>,
+    catalog_sources: &[String],
+    dry_run: bool,
+) -> Result<Config, String> {
+    if let Some(release) = release {
+        return get_target_configs(client, &[release], catalog_sources, dry_run)
+            .await?
+            .pop()
+            .ok_or_else(|| format!("No config for {}", release))?;
+    }
+
     let mode = ask_download_mode(client)?;
 
     if mode == "custom" {
         return get_custom_config(client);
     }
 
-    let selected_index_url = fetch_gist(client).await?;
+    let selected_index_url = fetch_gist(client, catalog_sources, dry_run).await?;
 
     clear_screen();
-    println!("{} Fetching download configuration...", Status::info());
+    load_config(client, selected_index_url, None).await
+}
 
-    let response = client
-        .get(&selected_index_url)
-        .timeout(Duration::from_secs(30))
-        .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+pub async fn load_config(
+    client: &Client,
+    selected_index_url: String,
+    section: Option<&str>,
+) -> Result<Config, String> {
+    println!("{} Fetching download configuration...", Status::info());
 
-    if !response.status().is_success() {
-        return Err(format!("Server error: HTTP {}", response.status()));
+    let config_text = fetch_text(client, &selected_index_url).await?;
+    let (config, warnings) = LauncherConfig::parse(&config_text)?;
+    for warning in &warnings {
+        println!("{} {}", Status::warning(), warning);
     }
 
-    let config_text = decompress_if_gzipped(response).await?;
-    let config: Value = from_str(&config_text).map_err(|e| format!("Invalid JSON: {}", e))?;
+    let has_default = config.default.is_some();
+    let has_predownload = config.predownload.is_some();
 
-    let has_default = config.get("default").is_some();
-    let has_predownload = config.get("predownload").is_some();
-
-    let selected_config = match (has_default, has_predownload) {
-        (true, false) => {
-            println!("{} Using default.config", Status::info());
-            "default"
-        }
-        (false, true) => {
-            println!("{} Using predownload.config", Status::info());
-            "predownload"
+    let selected_config = match section {
+        Some(section) => {
+            if config.section(section).is_none() {
+                return Err(format!("No {} config in {}", section, selected_index_url));
+            }
+            section
         }
-        (true, true) => loop {
-            print!(
-                "{} Choose config to use (1=default, 2=predownload): ",
-                Status::question()
-            );
-            io::stdout()
-                .flush()
-                .map_err(|e| format!("Failed to flush stdout: {}", e))?;
-
-            let mut input = String::new();
-            io::stdin()
-                .read_line(&mut input)
-                .map_err(|e| format!("Failed to read input: {}", e))?;
-
-            match input.trim() {
-                "1" => break "default",
-                "2" => break "predownload",
-                _ => println!("{} Invalid choice, please enter 1 or 2", Status::error()),
+        None => match (has_default, has_predownload) {
+            (true, false) => {
+                println!("{} Using default.config", Status::info());
+                "default"
+            }
+            (false, true) => {
+                println!("{} Using predownload.config", Status::info());
+                "predownload"
+            }
+            (true, true) => loop {
+                print!(
+                    "{} Choose config to use (1=default, 2=predownload): ",
+                    Status::question()
+                );
+                io::stdout()
+                    .flush()
+                    .map_err(|e| format!("Failed to flush stdout: {}", e))?;
+
+                let mut input = String::new();
+                io::stdin()
+                    .read_line(&mut input)
+                    .map_err(|e| format!("Failed to read input: {}", e))?;
+
+                match input.trim() {
+                    "1" => break "default",
+                    "2" => break "predownload",
+                    _ => println!("{} Invalid choice, please enter 1 or 2", Status::error()),
+                }
+            },
+            (false, false) => {
+                return Err(
+                    "Neither default.config nor predownload.config found in response".to_string(),
+                );
             }
         },
-        (false, false) => {
-            return Err(
-                "Neither default.config nor predownload.config found in response".to_string(),
-            );
-        }
     };
 
     let config_data = config
-        .get(selected_config)
+        .section(selected_config)
         .ok_or_else(|| format!("Missing {} config in response", selected_config))?;
 
-    let base_config = config_data
-        .get("config")
-        .ok_or_else(|| format!("Missing config in {} response", selected_config))?;
-
-    let base_url = base_config
-        .get("baseUrl")
-        .and_then(Value::as_str)
-        .ok_or("Missing or invalid baseUrl")?;
-
-    let index_file = base_config
-        .get("indexFile")
-        .and_then(Value::as_str)
-        .ok_or("Missing or invalid indexFile")?;
-
-    let mut cdn_urls = Vec::new();
-    if let Some(cdn_list) = config_data.get("cdnList").and_then(Value::as_array) {
-        for cdn in cdn_list {
-            if let Some(url) = cdn.get("url").and_then(Value::as_str) {
-                cdn_urls.push(url.trim_end_matches('/').to_string());
-            }
-        }
+    let base_url = &config_data.config.base_url;
+    let index_file = &config_data.config.index_file;
+    let version = config.version(selected_config);
+
+    let cdn_urls: Vec<String> = if let Some(cdn_list) = &config_data.cdn_list {
+        cdn_list
+            .iter()
+            .map(|cdn| cdn.url.trim_end_matches('/').to_string())
+            .collect()
     } else {
         println!(
             "{} CDN list not found. Please enter CDN URLs manually.",
@@ -779,98 +1119,256 @@ pub async fn get_config(client: &Client) -> Result<Config, String> {
             .read_line(&mut input)
             .map_err(|e| format!("Failed to read input: {}", e))?;
 
-        cdn_urls = input
+        input
             .trim()
             .split(',')
             .map(|s| s.trim().trim_end_matches('/').to_string())
             .filter(|s| !s.is_empty())
-            .collect();
-    }
+            .collect()
+    };
 
     if cdn_urls.is_empty() {
         return Err("No valid CDN URLs found".to_string());
     }
 
-    let full_index_url = build_download_url(&cdn_urls[0], index_file);
+    let index_mirrors: Vec<String> = cdn_urls
+        .iter()
+        .map(|cdn| build_download_url(cdn, index_file))
+        .collect();
     let zip_bases = cdn_urls
         .iter()
         .map(|cdn| build_download_url(cdn, base_url))
         .collect();
 
+    let patch_list = if config_data.config.patch_config.is_empty() {
+        &config_data.patch_config
+    } else {
+        &config_data.config.patch_config
+    };
+    let patches = patch_list
+        .iter()
+        .map(|patch| {
+            let index_mirrors: Vec<String> = cdn_urls
+                .iter()
+                .map(|cdn| build_download_url(cdn, &patch.index_file))
+                .collect();
+            PatchSource {
+                version: patch.version.clone(),
+                index_url: index_mirrors[0].clone(),
+                index_mirrors,
+                zip_bases: cdn_urls
+                    .iter()
+                    .map(|cdn| build_download_url(cdn, &patch.base_url))
+                    .collect(),
+            }
+        })
+        .collect();
+
     Ok(Config {
-        index_url: full_index_url,
+        index_url: index_mirrors[0].clone(),
+        index_mirrors,
         zip_bases,
+        version,
+        source: Some(ConfigSource {
+            url: selected_index_url,
+            section: selected_config.to_string(),
+        }),
+        patches,
     })
 }
 
-pub async fn fetch_gist(client: &Client) -> Result<String, String> {
-    let response = client
-        .get(INDEX_URL)
-        .timeout(Duration::from_secs(30))
-        .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
-
-    if !response.status().is_success() {
-        return Err(format!("Server error: HTTP {}", response.status()));
-    }
+// The selected index plus the other section of the same launcher config, for matching what is on disk.
+pub async fn candidate_configs(client: &Client, config: &Config) -> Vec<Config> {
+    let mut candidates = vec![config.clone()];
+    let Some(source) = &config.source else {
+        return candidates;
+    };
 
-    let gist_data_text = decompress_if_gzipped(response).await?;
-    let gist_data: Value = from_str(&gist_data_text).map_err(|e| format!("Invalid JSON: {}", e))?;
+    let Ok((launcher, _)) = fetch_text(client, &source.url)
+        .await
+        .and_then(|text| LauncherConfig::parse(&text))
+    else {
+        return candidates;
+    };
 
-    clear_screen();
+    let other = if source.section == "predownload" {
+        "default"
+    } else {
+        "predownload"
+    };
+    let Some(section) = launcher.section(other) else {
+        return candidates;
+    };
 
-    let entries = [
-        ("live", "os", "Live - OS"),
-        ("live", "cn", "Live - CN"),
-        ("beta", "os", "Beta - OS"),
-        ("beta", "cn", "Beta - CN"),
-    ];
+    let cdn_list = section
+        .cdn_list
+        .as_ref()
+        .or_else(|| launcher.section(&source.section)?.cdn_list.as_ref());
+    let index_mirrors: Vec<String> = cdn_list
+        .into_iter()
+        .flatten()
+        .map(|cdn| build_download_url(cdn.url.trim_end_matches('/'), &section.config.index_file))
+        .collect();
 
-    println!("{} Available versions:", Status::info());
+    if let Some(index_url) = index_mirrors.first() {
+        candidates.push(Config {
+            index_url: index_url.clone(),
+            index_mirrors,
+            version: launcher.version(other),
+            source: Some(ConfigSource {
+                url: source.url.clone(),
+                section: other.to_string(),
+            }),
+            ..Default::default()
+        });
+    }
 
-    for (i, (cat, ver, label)) in entries.iter().enumerate() {
-        let index_url = get_version(&gist_data, cat, ver)?;
+    candidates
+}
 
-        let resp = match client.get(&index_url).send().await {
-            Ok(resp) => resp,
-            Err(e) => {
-                println!("{} Failed to fetch {}: {}", Status::warning(), index_url, e);
-                continue;
+// Targets are catalog names such as `live-os`, optionally with `:predownload`.
+pub async fn get_target_configs(
+    client: &Client,
+    specs: &[&str],
+    catalog_sources: &[String],
+    dry_run: bool,
+) -> Result<Vec<Result<Config, String>>, String> {
+    let catalog = fetch_catalog(client, catalog_sources, dry_run).await?;
+    let mut configs = Vec::with_capacity(specs.len());
+
+    // A bad spec only fails its own target, the rest of the queue still runs.
+    for spec in specs {
+        let (name, section) = spec.split_once(':').unwrap_or((spec, "default"));
+        let config = if matches!(section, "default" | "predownload") {
+            match catalog.find(name) {
+                Ok(entry) => load_config(client, entry.index_url.clone(), Some(section)).await,
+                Err(e) => Err(e),
             }
+        } else {
+            Err(format!("Unknown config {:?} in target {:?}", section, spec))
         };
+        configs.push(config);
+    }
 
-        let version_json: Value = {
-            let version_text = decompress_if_gzipped(resp)
-                .await
-                .unwrap_or_else(|_| "{}".to_string());
-            from_str(&version_text).unwrap_or(Value::Null)
-        };
+    Ok(configs)
+}
 
-        let version = version_json
-            .get("default")
-            .and_then(|d| d.get("config"))
-            .and_then(|c| c.get("version"))
-            .or_else(|| version_json.get("default").and_then(|d| d.get("version")))
-            .and_then(|v| v.as_str())
-            .unwrap_or("unknown");
+pub async fn fetch_gist(
+    client: &Client,
+    catalog_sources: &[String],
+    dry_run: bool,
+) -> Result<String, String> {
+    let catalog = fetch_catalog(client, catalog_sources, dry_run).await?;
+    let versions = probe_versions(client, &catalog.entries).await;
 
-        println!("{}. {} ({})", i + 1, label, version);
+    clear_screen();
+    println!("{} Available versions:", Status::info());
+
+    for (i, (entry, version)) in catalog.entries.iter().zip(&versions).enumerate() {
+        let version = version.as_deref().unwrap_or("unknown");
+        match &entry.note {
+            Some(note) => println!(
+                "{}. {} ({}) [{}] - {}",
+                i + 1,
+                entry.label,
+                version,
+                entry.name,
+                note
+            ),
+            None => println!("{}. {} ({}) [{}]", i + 1, entry.label, version, entry.name),
+        }
     }
 
     loop {
-        print!("{} Select version: ", Status::question());
+        print!("{} Select version (number or name): ", Status::question());
         io::stdout().flush().unwrap();
 
         let mut input = String::new();
         io::stdin().read_line(&mut input).unwrap();
+        let input = input.trim();
+
+        let selected = match input.parse::<usize>() {
+            Ok(n) => n
+                .checked_sub(1)
+                .and_then(|i| catalog.entries.get(i))
+                .ok_or_else(|| "Invalid selection".to_string()),
+            Err(_) => catalog.find(input),
+        };
 
-        match input.trim() {
-            "1" => return get_version(&gist_data, "live", "os"),
-            "2" => return get_version(&gist_data, "live", "cn"),
-            "3" => return get_version(&gist_data, "beta", "os"),
-            "4" => return get_version(&gist_data, "beta", "cn"),
-            _ => println!("{} Invalid selection", Status::error()),
+        match selected {
+            Ok(entry) => return Ok(entry.index_url.clone()),
+            Err(e) => println!("{} {}", Status::error(), e),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(source: &str) -> Result<PathBuf, String> {
+        local_source(source).expect("should be a local source")
+    }
+
+    #[test]
+    fn http_urls_are_not_local() {
+        assert!(local_source("https://cdn.example.com/index.json").is_none());
+        assert!(local_source("http://cdn.example.com/index.json").is_none());
+    }
+
+    #[test]
+    fn plain_paths_are_kept() {
+        assert_eq!(
+            local("/mnt/cdn/index.json").unwrap(),
+            Path::new("/mnt/cdn/index.json")
+        );
+        assert_eq!(
+            local("cdn/My%20Files").unwrap(),
+            Path::new("cdn/My%20Files")
+        );
+    }
+
+    #[test]
+    fn file_urls_are_percent_decoded() {
+        assert_eq!(
+            local("file:///mnt/game%20cdn/index.json").unwrap(),
+            Path::new("/mnt/game cdn/index.json")
+        );
+        assert_eq!(
+            local("file://localhost/mnt/cdn/%E6%B8%B8%E6%88%8F.json").unwrap(),
+            Path::new("/mnt/cdn/游戏.json")
+        );
+        assert!(local("file:///mnt/%FF.json").is_err());
+        assert!(local("file://").is_err());
+    }
+
+    #[test]
+    fn other_schemes_are_rejected() {
+        let error = local("ftp://mirror/index.json").err().unwrap();
+        assert!(error.contains("Unsupported URL scheme"), "{}", error);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn windows_file_urls_map_to_drives_and_shares() {
+        assert_eq!(
+            local("file:///C:/Games/Wuthering%20Waves").unwrap(),
+            Path::new(r"C:/Games/Wuthering Waves")
+        );
+        assert_eq!(
+            local("file://nas/share/cdn/index.json").unwrap(),
+            Path::new(r"\\nas\share\cdn\index.json")
+        );
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn windows_only_file_urls_are_rejected_elsewhere() {
+        let error = local("file:///C:/Games/index.json").err().unwrap();
+        assert!(error.contains("only valid on Windows"), "{}", error);
+
+        let error = local("file://nas/share/index.json").err().unwrap();
+        assert!(error.contains("nas"), "{}", error);
+        assert!(error.contains("mount it"), "{}", error);
+    }
+}
@@ -1 +1,3 @@
+pub mod catalog;
 pub mod client;
+pub mod models;
@@ -0,0 +1,383 @@
+use serde::{
+    Deserialize, Deserializer,
+    de::{DeserializeOwned, Error, MapAccess, Visitor},
+};
+use serde_json::Value;
+use std::{collections::BTreeSet, fmt, marker::PhantomData};
+
+// serde maps lose the file order, which the version menu relies on.
+pub struct OrderedMap<T>(pub Vec<(String, T)>);
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for OrderedMap<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct OrderedMapVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for OrderedMapVisitor<T> {
+            type Value = OrderedMap<T>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut entries = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(OrderedMap(entries))
+            }
+        }
+
+        deserializer.deserialize_map(OrderedMapVisitor(PhantomData))
+    }
+}
+
+pub enum CatalogRegion {
+    Url(String),
+    Detailed(CatalogRelease),
+}
+
+// Dispatching by hand keeps errors like a misspelled field, which `untagged` would swallow.
+impl<'de> Deserialize<'de> for CatalogRegion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match Value::deserialize(deserializer)? {
+            Value::String(url) => Ok(Self::Url(url)),
+            value @ Value::Object(_) => CatalogRelease::deserialize(value)
+                .map(Self::Detailed)
+                .map_err(D::Error::custom),
+            _ => Err(D::Error::custom(
+                "expected a URL or an object with url, label and note",
+            )),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CatalogRelease {
+    pub url: String,
+    pub label: Option<String>,
+    pub note: Option<String>,
+}
+
+pub type CatalogFile = OrderedMap<OrderedMap<CatalogRegion>>;
+
+#[derive(Deserialize)]
+pub struct LauncherConfig {
+    pub default: Option<LauncherSection>,
+    pub predownload: Option<LauncherSection>,
+}
+
+impl LauncherConfig {
+    // Returns the config plus warnings about entries and fields that were left out.
+    pub fn parse(text: &str) -> Result<(Self, Vec<String>), String> {
+        let value = parse_json(text, "launcher config")?;
+        Self::from_json(value)
+    }
+
+    pub fn from_json(mut value: Value) -> Result<(Self, Vec<String>), String> {
+        let mut warnings = Vec::new();
+        for name in ["default", "predownload"] {
+            let Some(section) = value.get_mut(name) else {
+                continue;
+            };
+            drop_invalid::<Cdn>(section, &["cdnList"], name, &mut warnings);
+            drop_invalid::<PatchConfig>(section, &["patchConfig"], name, &mut warnings);
+            drop_invalid::<PatchConfig>(section, &["config", "patchConfig"], name, &mut warnings);
+        }
+
+        let (config, unknown) = from_value_checked(&value, "launcher config")?;
+        warnings.extend(unknown);
+        Ok((config, warnings))
+    }
+
+    pub fn section(&self, name: &str) -> Option<&LauncherSection> {
+        match name {
+            "default" => self.default.as_ref(),
+            "predownload" => self.predownload.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn version(&self, section: &str) -> Option<String> {
+        let section = self.section(section)?;
+        section
+            .config
+            .version
+            .clone()
+            .or_else(|| section.version.clone())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LauncherSection {
+    pub config: ResourceConfig,
+    pub cdn_list: Option<Vec<Cdn>>,
+    #[serde(default)]
+    pub patch_config: Vec<PatchConfig>,
+    pub version: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceConfig {
+    pub base_url: String,
+    pub index_file: String,
+    pub version: Option<String>,
+    #[serde(default)]
+    pub patch_config: Vec<PatchConfig>,
+}
+
+#[derive(Deserialize)]
+pub struct Cdn {
+    pub url: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchConfig {
+    pub version: String,
+    pub base_url: String,
+    pub index_file: String,
+}
+
+#[derive(Deserialize)]
+pub struct IndexFile {
+    pub resource: Vec<IndexEntry>,
+}
+
+#[derive(Deserialize)]
+pub struct IndexEntry {
+    pub dest: Option<String>,
+    pub md5: Option<String>,
+    #[serde(default, deserialize_with = "size_value")]
+    pub size: Option<u64>,
+}
+
+// Some indexes store sizes as strings.
+fn size_value<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Size {
+        Number(u64),
+        Text(String),
+    }
+
+    match Option::<Size>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(Size::Number(size)) => Ok(Some(size)),
+        Some(Size::Text(text)) => text
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| D::Error::custom(format!("invalid size {:?}", text))),
+    }
+}
+
+// A malformed mirror or patch entry only costs that entry, not the whole config.
+fn drop_invalid<T: DeserializeOwned>(
+    section: &mut Value,
+    keys: &[&str],
+    section_name: &str,
+    warnings: &mut Vec<String>,
+) {
+    let list = keys
+        .iter()
+        .try_fold(section, |value, key| value.get_mut(*key));
+    let Some(Value::Array(items)) = list else {
+        return;
+    };
+
+    let before = items.len();
+    let mut first_error = None;
+    items.retain(|item| match T::deserialize(item) {
+        Ok(_) => true,
+        Err(e) => {
+            first_error.get_or_insert_with(|| e.to_string());
+            false
+        }
+    });
+
+    if let Some(error) = first_error {
+        warnings.push(format!(
+            "Skipped {} of {} entries in {}.{} ({})",
+            before - items.len(),
+            before,
+            section_name,
+            keys.join("."),
+            error
+        ));
+    }
+}
+
+// List indexes are folded, so a field repeated in every index entry is named once.
+fn field_path(path: &serde_ignored::Path) -> String {
+    use serde_ignored::Path;
+
+    match path {
+        Path::Root => String::new(),
+        Path::Seq { parent, .. } => format!("{}[]", field_path(parent)),
+        Path::Map { parent, key } => match field_path(parent) {
+            parent if parent.is_empty() => key.clone(),
+            parent => format!("{}.{}", parent, key),
+        },
+        Path::Some { parent }
+        | Path::NewtypeStruct { parent }
+        | Path::NewtypeVariant { parent } => field_path(parent),
+    }
+}
+
+fn describe<E: fmt::Display>(what: &str, error: serde_path_to_error::Error<E>) -> String {
+    let path = error.path().to_string();
+    if path == "." {
+        format!("Invalid {}: {}", what, error.inner())
+    } else {
+        format!("Invalid {} at {}: {}", what, path, error.inner())
+    }
+}
+
+pub fn parse_json<T: DeserializeOwned>(text: &str, what: &str) -> Result<T, String> {
+    let deserializer = &mut serde_json::Deserializer::from_str(text);
+    serde_path_to_error::deserialize(deserializer).map_err(|e| describe(what, e))
+}
+
+pub fn from_value<T: DeserializeOwned>(value: &Value, what: &str) -> Result<T, String> {
+    serde_path_to_error::deserialize(value).map_err(|e| describe(what, e))
+}
+
+// Like `from_value`, but fields the models do not know come back as a warning instead of vanishing.
+pub fn from_value_checked<T: DeserializeOwned>(
+    value: &Value,
+    what: &str,
+) -> Result<(T, Vec<String>), String> {
+    let mut unknown = BTreeSet::new();
+    let mut callback = |path: serde_ignored::Path| {
+        unknown.insert(field_path(&path));
+    };
+    let deserializer = serde_ignored::Deserializer::new(value, &mut callback);
+    let parsed = serde_path_to_error::deserialize(deserializer).map_err(|e| describe(what, e))?;
+
+    const SHOWN: usize = 8;
+    let warning = match unknown.len() {
+        0 => None,
+        count if count <= SHOWN => Some(format!(
+            "Ignored unknown fields in {}: {}",
+            what,
+            unknown.into_iter().collect::<Vec<_>>().join(", ")
+        )),
+        count => Some(format!(
+            "Ignored unknown fields in {}: {} and {} more",
+            what,
+            unknown
+                .into_iter()
+                .take(SHOWN)
+                .collect::<Vec<_>>()
+                .join(", "),
+            count - SHOWN
+        )),
+    };
+    Ok((parsed, warning.into_iter().collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entry(value: Value) -> Result<IndexEntry, String> {
+        from_value(&value, "index entry")
+    }
+
+    #[test]
+    fn size_value_accepts_numbers_and_strings() {
+        assert_eq!(entry(json!({ "size": 42 })).unwrap().size, Some(42));
+        assert_eq!(entry(json!({ "size": "42" })).unwrap().size, Some(42));
+        assert_eq!(entry(json!({ "size": " 7 " })).unwrap().size, Some(7));
+        assert_eq!(entry(json!({ "size": null })).unwrap().size, None);
+        assert_eq!(entry(json!({})).unwrap().size, None);
+    }
+
+    #[test]
+    fn size_value_rejects_other_values() {
+        let error = entry(json!({ "size": "12 MB" })).err().unwrap();
+        assert!(error.contains("at size"), "{}", error);
+        assert!(error.contains("invalid size"), "{}", error);
+        assert!(entry(json!({ "size": -1 })).is_err());
+        assert!(entry(json!({ "size": true })).is_err());
+    }
+
+    #[test]
+    fn unknown_index_fields_are_named_once() {
+        let value = json!({
+            "resource": [
+                { "dest": "a.pak", "md5": "x", "size": 1, "sampleHash": "y" },
+                { "dest": "b.pak", "md5": "x", "size": 1, "sampleHash": "z" }
+            ],
+            "version": 2
+        });
+        let (index, warnings): (IndexFile, _) = from_value_checked(&value, "index file").unwrap();
+
+        assert_eq!(index.resource.len(), 2);
+        assert_eq!(
+            warnings,
+            ["Ignored unknown fields in index file: resource[].sampleHash, version"]
+        );
+    }
+
+    #[test]
+    fn launcher_config_skips_bad_optional_entries() {
+        let text = json!({
+            "default": {
+                "config": {
+                    "baseUrl": "zip",
+                    "indexFile": "index.json",
+                    "patchConfig": [
+                        { "version": "1.0", "baseUrl": "p", "indexFile": "p.json" },
+                        { "version": "0.9" }
+                    ]
+                },
+                "cdnList": [{ "url": "https://a" }, { "uri": "https://b" }, "https://c"],
+                "version": "1.1"
+            }
+        })
+        .to_string();
+
+        let (config, warnings) = LauncherConfig::parse(&text).unwrap();
+        let section = config.section("default").unwrap();
+        assert_eq!(section.cdn_list.as_ref().unwrap().len(), 1);
+        assert_eq!(section.config.patch_config.len(), 1);
+        assert_eq!(config.version("default").as_deref(), Some("1.1"));
+
+        assert_eq!(warnings.len(), 2, "{:?}", warnings);
+        assert!(warnings[0].starts_with("Skipped 2 of 3 entries in default.cdnList"));
+        assert!(warnings[1].starts_with("Skipped 1 of 2 entries in default.config.patchConfig"));
+    }
+
+    #[test]
+    fn launcher_config_reports_unknown_section_fields() {
+        let text = json!({
+            "default": {
+                "config": { "baseUrl": "zip", "indexFile": "index.json", "indexFileMd5": "x" },
+                "changelog": {}
+            }
+        })
+        .to_string();
+
+        let (_, warnings) = LauncherConfig::parse(&text).unwrap();
+        assert_eq!(
+            warnings,
+            [
+                "Ignored unknown fields in launcher config: default.changelog, default.config.indexFileMd5"
+            ]
+        );
+    }
+
+    #[test]
+    fn required_fields_still_fail_with_a_path() {
+        let text = json!({ "default": { "config": { "indexFile": "index.json" } } }).to_string();
+        let error = LauncherConfig::parse(&text).err().unwrap();
+        assert!(error.contains("default.config"), "{}", error);
+        assert!(error.contains("baseUrl"), "{}", error);
+    }
+}